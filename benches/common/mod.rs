@@ -0,0 +1,24 @@
+use rust_knowledge_search::index::Index;
+use rust_knowledge_search::ingestion::Document;
+use std::path::PathBuf;
+
+// Synthetic corpus big enough for JSON parsing to dominate start-up, shared
+// by the first_query and view benches so their numbers compare.
+pub fn build_index(doc_count: usize) -> Index {
+    let mut index = Index::new();
+
+    for i in 0..doc_count {
+        let content = (0..200)
+            .map(|w| format!("term{} word{}", (i * 7 + w) % 5000, w % 50))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        index.add_document(Document::file(
+            PathBuf::from(format!("notes/note_{i}.md")),
+            content,
+            None,
+        ));
+    }
+
+    index
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::build_index;
+use criterion::{Criterion, criterion_group, criterion_main};
+use rust_knowledge_search::index::Index;
+use std::hint::black_box;
+use uuid::Uuid;
+
+fn first_query_latency(c: &mut Criterion) {
+    let index = build_index(2_000);
+    let dir = std::env::temp_dir();
+    let json_path = dir.join(format!("bench_{}.json", Uuid::new_v4()));
+    let mmap_path = dir.join(format!("bench_{}.mmap", Uuid::new_v4()));
+
+    index.save_to_disk(&json_path).unwrap();
+    index.save_mmap(&mmap_path).unwrap();
+
+    let mut group = c.benchmark_group("first_query");
+    group.sample_size(10);
+
+    // Open + one query, i.e. what a short-lived CLI invocation pays
+    group.bench_function("json_load_then_search", |b| {
+        b.iter(|| {
+            let index = Index::load_from_disk(&json_path).unwrap();
+            black_box(index.search_query("term42 word7"))
+        })
+    });
+
+    group.bench_function("mmap_open_then_search", |b| {
+        b.iter(|| {
+            let view = Index::open_mmap(&mmap_path).unwrap();
+            black_box(view.search_query("term42 word7"))
+        })
+    });
+
+    group.finish();
+
+    let _ = std::fs::remove_file(json_path);
+    let _ = std::fs::remove_file(mmap_path);
+}
+
+criterion_group!(benches, first_query_latency);
+criterion_main!(benches);
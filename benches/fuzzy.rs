@@ -0,0 +1,79 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use rust_knowledge_search::index::Index;
+use std::hint::black_box;
+
+// Deterministic pseudo-random words, 4-10 lowercase letters each.
+fn vocabulary(count: usize) -> Vec<String> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    (0..count)
+        .map(|_| {
+            let len = 4 + (next() % 7) as usize;
+            (0..len)
+                .map(|_| (b'a' + (next() % 26) as u8) as char)
+                .collect()
+        })
+        .collect()
+}
+
+// The O(vocabulary x term length) baseline: full edit distance per term.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let mut row: Vec<usize> = (0..=a.len()).collect();
+
+    for (j, b_ch) in b.chars().enumerate() {
+        let mut next = vec![j + 1];
+        for (i, a_ch) in a.iter().enumerate() {
+            next.push(
+                (row[i] + usize::from(*a_ch != b_ch))
+                    .min(next[i] + 1)
+                    .min(row[i + 1] + 1),
+            );
+        }
+        row = next;
+    }
+
+    row[a.len()]
+}
+
+fn fuzzy_lookup(c: &mut Criterion) {
+    let words = vocabulary(100_000);
+
+    let mut index = Index::new();
+    for chunk in words.chunks(1_000) {
+        index.add_text(&chunk.join(" "));
+    }
+
+    let mut sorted = words.clone();
+    sorted.sort();
+    sorted.dedup();
+
+    let query = "knowledge";
+    let mut group = c.benchmark_group("fuzzy_terms");
+
+    group.bench_function("naive_scan", |b| {
+        b.iter(|| {
+            black_box(
+                sorted
+                    .iter()
+                    .filter(|term| levenshtein(query, term) <= 2)
+                    .count(),
+            )
+        })
+    });
+
+    group.bench_function("levenshtein_automaton", |b| {
+        b.iter(|| black_box(index.fuzzy_terms(query, 2).len()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, fuzzy_lookup);
+criterion_main!(benches);
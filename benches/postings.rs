@@ -0,0 +1,57 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rust_knowledge_search::index::PostingList;
+use rust_knowledge_search::ingestion::DocumentId;
+use std::collections::HashSet;
+use std::hint::black_box;
+
+// Two overlapping posting lists of `len` ids each, sharing half of them.
+fn overlapping_ids(len: usize) -> (Vec<DocumentId>, Vec<DocumentId>) {
+    let shared: Vec<DocumentId> = (0..len / 2).map(|_| DocumentId::random()).collect();
+
+    let unique = len - shared.len();
+
+    let mut a = shared.clone();
+    a.extend((0..unique).map(|_| DocumentId::random()));
+    let mut b = shared;
+    b.extend((0..unique).map(|_| DocumentId::random()));
+
+    (a, b)
+}
+
+fn set_operations(c: &mut Criterion) {
+    let mut group = c.benchmark_group("postings");
+
+    // Typical note terms are rare; common terms hit thousands of documents
+    for len in [8, 128, 4_096] {
+        let (a, b) = overlapping_ids(len);
+
+        let set_a: HashSet<DocumentId> = a.iter().copied().collect();
+        let set_b: HashSet<DocumentId> = b.iter().copied().collect();
+        let list_a: PostingList = a.into_iter().collect();
+        let list_b: PostingList = b.into_iter().collect();
+
+        group.bench_with_input(
+            BenchmarkId::new("hash_set_intersection", len),
+            &len,
+            |bench, _| bench.iter(|| black_box(set_a.intersection(&set_b).count())),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("sorted_vec_intersection", len),
+            &len,
+            |bench, _| bench.iter(|| black_box(list_a.intersection(&list_b).count())),
+        );
+        group.bench_with_input(BenchmarkId::new("hash_set_union", len), &len, |bench, _| {
+            bench.iter(|| black_box(set_a.union(&set_b).count()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("sorted_vec_union", len),
+            &len,
+            |bench, _| bench.iter(|| black_box(list_a.union(&list_b).count())),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, set_operations);
+criterion_main!(benches);
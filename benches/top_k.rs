@@ -0,0 +1,62 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rust_knowledge_search::index::Index;
+use rust_knowledge_search::ingestion::Document;
+use std::hint::black_box;
+use std::path::PathBuf;
+
+// Every document mentions "common", a varying number of times, so a query
+// for it matches (and scores) the whole corpus.
+fn build_index(doc_count: usize) -> Index {
+    let mut index = Index::new();
+
+    for i in 0..doc_count {
+        let content = (0..50)
+            .map(|w| {
+                if w % (i % 10 + 2) == 0 {
+                    "common".to_string()
+                } else {
+                    format!("term{}", (i * 7 + w) % 5000)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        index.add_document(Document::file(
+            PathBuf::from(format!("notes/note_{i}.md")),
+            content,
+            None,
+        ));
+    }
+
+    index
+}
+
+fn top_k(c: &mut Criterion) {
+    let mut group = c.benchmark_group("top_k");
+
+    for doc_count in [1_000, 10_000] {
+        let index = build_index(doc_count);
+
+        group.bench_with_input(
+            BenchmarkId::new("search_ranked_truncate", doc_count),
+            &doc_count,
+            |b, _| {
+                b.iter(|| {
+                    let mut results = index.search_ranked("common");
+                    results.truncate(20);
+                    black_box(results)
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("search_top_k", doc_count),
+            &doc_count,
+            |b, _| b.iter(|| black_box(index.search_top_k("common", 20))),
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, top_k);
+criterion_main!(benches);
@@ -0,0 +1,34 @@
+mod common;
+
+use common::build_index;
+use criterion::{Criterion, criterion_group, criterion_main};
+use rust_knowledge_search::index::{Index, IndexView};
+use std::hint::black_box;
+use uuid::Uuid;
+
+fn warm_search(c: &mut Criterion) {
+    let index = build_index(2_000);
+    let mmap_path = std::env::temp_dir().join(format!("bench_{}.mmap", Uuid::new_v4()));
+    index.save_mmap(&mmap_path).unwrap();
+
+    let mmap = Index::open_mmap(&mmap_path).unwrap();
+    let view: IndexView<'_> = mmap.view();
+
+    // Already loaded, so this is the per-query cost a long-lived server pays
+    let mut group = c.benchmark_group("warm_search");
+
+    group.bench_function("owned_index", |b| {
+        b.iter(|| black_box(index.search_query("term42 word7")))
+    });
+
+    group.bench_function("borrowed_view", |b| {
+        b.iter(|| black_box(view.search_query("term42 word7")))
+    });
+
+    group.finish();
+
+    let _ = std::fs::remove_file(mmap_path);
+}
+
+criterion_group!(benches, warm_search);
+criterion_main!(benches);
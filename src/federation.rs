@@ -0,0 +1,169 @@
+//! Searching several indexes (shards) as if they were one.
+
+use crate::index::{Index, IndexError};
+use crate::ingestion::DocumentId;
+use std::cmp::Ordering;
+use std::path::Path;
+
+/// A set of named indexes, e.g. `work`, `personal` and `archive`, searched
+/// together with one merged ranking.
+#[derive(Debug, Default)]
+pub struct Federation {
+    shards: Vec<Shard>,
+}
+
+#[derive(Debug)]
+struct Shard {
+    name: String,
+    index: Index,
+}
+
+/// One result of `Federation::search_ranked`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FederatedHit {
+    /// Name of the shard the document lives in
+    pub shard: String,
+    pub id: DocumentId,
+    pub score: f32,
+}
+
+impl Federation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an in-memory index under `name`.
+    pub fn add_shard(&mut self, name: impl Into<String>, index: Index) {
+        self.shards.push(Shard {
+            name: name.into(),
+            index,
+        });
+    }
+
+    /// Loads an index file saved with `Index::save_to_disk` as a shard.
+    pub fn load_shard<P: AsRef<Path>>(
+        &mut self,
+        name: impl Into<String>,
+        path: P,
+    ) -> Result<(), IndexError> {
+        self.add_shard(name, Index::load_from_disk(path)?);
+        Ok(())
+    }
+
+    /// The index behind a shard, e.g. to look up a hit's document.
+    pub fn shard(&self, name: &str) -> Option<&Index> {
+        self.shards
+            .iter()
+            .find(|shard| shard.name == name)
+            .map(|shard| &shard.index)
+    }
+
+    /// Ranks every shard's matches together, best first.
+    ///
+    /// Each shard scores with its own statistics (IDF, average length), so
+    /// scores are comparable as long as shards are built the same way and
+    /// aren't wildly different in size. Ties go to the earlier shard, then
+    /// the document path.
+    pub fn search_ranked(&self, query: &str) -> Vec<FederatedHit> {
+        // 1. Score per shard, remembering where each hit came from
+        let mut hits: Vec<(usize, DocumentId, f32)> = Vec::new();
+        for (position, shard) in self.shards.iter().enumerate() {
+            hits.extend(
+                shard
+                    .index
+                    .score_map(query)
+                    .into_iter()
+                    .map(|(id, score)| (position, id, score)),
+            );
+        }
+
+        // 2. One global order across all shards
+        hits.sort_by(|a, b| {
+            b.2.total_cmp(&a.2)
+                .then_with(|| a.0.cmp(&b.0))
+                .then_with(|| self.compare_path(a.0, a.1, b.1))
+        });
+
+        hits.into_iter()
+            .map(|(position, id, score)| FederatedHit {
+                shard: self.shards[position].name.clone(),
+                id,
+                score,
+            })
+            .collect()
+    }
+
+    // Path order within one shard, id as a last resort.
+    fn compare_path(&self, shard: usize, a: DocumentId, b: DocumentId) -> Ordering {
+        let index = &self.shards[shard].index;
+        let path_a = index.document(a).map(|doc| &doc.path);
+        let path_b = index.document(b).map(|doc| &doc.path);
+
+        path_a.cmp(&path_b).then_with(|| a.cmp(&b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn federated_results_are_ranked_globally() {
+        let mut work = Index::new();
+        let work_strong = work.add_text("rust rust rust async");
+        let work_weak = work.add_text("rust meeting notes with lots of other words in it");
+        work.add_text("quarterly planning");
+
+        let mut personal = Index::new();
+        let personal_mid = personal.add_text("learning rust at home");
+        personal.add_text("gardening");
+        personal.add_text("recipes");
+
+        let expected_scores = {
+            let mut all: Vec<f32> = work
+                .score_map("rust")
+                .into_values()
+                .chain(personal.score_map("rust").into_values())
+                .collect();
+            all.sort_by(|a, b| b.total_cmp(a));
+            all
+        };
+
+        let mut federation = Federation::new();
+        federation.add_shard("work", work);
+        federation.add_shard("personal", personal);
+
+        let hits = federation.search_ranked("rust");
+        let scores: Vec<f32> = hits.iter().map(|hit| hit.score).collect();
+        assert_eq!(scores, expected_scores);
+
+        // Every hit is labelled with the shard that owns it
+        let owner = |id: DocumentId| hits.iter().find(|hit| hit.id == id).unwrap().shard.clone();
+        assert_eq!(owner(work_strong), "work");
+        assert_eq!(owner(work_weak), "work");
+        assert_eq!(owner(personal_mid), "personal");
+        assert_eq!(hits[0].id, work_strong);
+
+        assert!(federation.shard("personal").is_some());
+        assert!(federation.search_ranked("missing").is_empty());
+    }
+
+    #[test]
+    fn load_shard_reads_saved_indexes() {
+        let mut index = Index::new();
+        let id = index.add_text("archived rust notes");
+        let path = std::env::temp_dir().join(format!("shard_{}.json", Uuid::new_v4()));
+        index.save_to_disk(&path).unwrap();
+
+        let mut federation = Federation::new();
+        federation.load_shard("archive", &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let hits = federation.search_ranked("archived");
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].shard.as_str(), hits[0].id), ("archive", id));
+
+        assert!(federation.load_shard("missing", &path).is_err());
+    }
+}
@@ -1,57 +1,618 @@
-use crate::ingestion::Document;
-use crate::tokenizer::tokenize;
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::ingestion::{
+    Document, DocumentId, DocumentSource, IngestError, load_document, normalize_path, read_content,
+};
+use crate::tokenizer::{
+    StreamTokenizer, TokenStrategy, TokenizerOptions, tokenize_ngrams, tokenize_with,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::io;
+use std::io::{self, Read};
 use std::path::Path;
 use std::path::PathBuf;
-use uuid::Uuid;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+
+mod boost;
+mod cache;
+mod compact;
+mod fields;
+mod fuzzy;
+mod incremental;
+mod mmap;
+mod ndjson;
+mod pattern;
+mod postings;
+mod proximity;
+mod ranking;
+mod reconcile;
+mod snippet;
+mod tree;
+mod verify;
+use cache::QueryCache;
+pub use fields::{Field, FieldTerm};
+use incremental::DirtySet;
+pub use mmap::{IndexView, MmapIndex};
+pub use postings::PostingList;
+pub use ranking::{RankedMatch, ScoringMode};
+pub use reconcile::ReconcileReport;
+pub use tree::ResultTree;
+
+// Bytes read per call in `add_document_streaming`.
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Format version written with every index file. Bump it whenever a change
+/// to `Index` can't be read back from older files (a new field with
+/// `#[serde(default)]` doesn't need one).
+pub const INDEX_VERSION: u32 = 1;
+
+// Files saved before the version was recorded
+fn unversioned() -> u32 {
+    1
+}
+
+#[derive(Debug, Error)]
+pub enum IndexError {
+    /// The index couldn't be turned into JSON or read back from it.
+    #[error("index (de)serialization failed: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A binary index file didn't have the expected layout.
+    #[error("invalid index file: {0}")]
+    InvalidFormat(&'static str),
+    /// The internal maps disagree with each other (see `Index::verify`).
+    #[error("index is inconsistent: {0}")]
+    Inconsistent(String),
+    /// The file was written by an incompatible version of the index format.
+    #[error("index version mismatch (file is v{found}, expected v{expected}), please reindex")]
+    VersionMismatch { found: u32, expected: u32 },
+}
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Index {
-    postings: HashMap<String, HashSet<Uuid>>,
-    documents: HashMap<Uuid, Document>,
-    pub path_to_id: HashMap<PathBuf, Uuid>,
-    doc_tokens: HashMap<Uuid, HashSet<String>>,
+    #[serde(default = "unversioned")]
+    version: u32,
+    // Sorted by term so fuzzy matching can walk the vocabulary in order
+    postings: BTreeMap<String, PostingList>,
+    documents: HashMap<DocumentId, Document>,
+    // Several documents may share a path through `add_document`; only
+    // `upsert_document` replaces by path. Older files mapped to one id.
+    #[serde(alias = "path_to_id", deserialize_with = "deserialize_path_ids")]
+    pub path_to_ids: HashMap<PathBuf, HashSet<DocumentId>>,
+    doc_tokens: HashMap<DocumentId, HashSet<String>>,
+    // How often each token occurs per document, used for ranking
+    #[serde(default)]
+    term_freqs: HashMap<DocumentId, HashMap<String, usize>>,
+    // Token positions per document, for proximity search
+    #[serde(default)]
+    positions: HashMap<DocumentId, HashMap<String, Vec<usize>>>,
+    // Total token count per document (BM25 length normalization)
+    #[serde(default)]
+    doc_lengths: HashMap<DocumentId, usize>,
+    // When each document was last (re)indexed, as opposed to file mtime
+    #[serde(default)]
+    indexed_at: HashMap<DocumentId, SystemTime>,
+    // Query-time expansions, e.g. "car" -> ["automobile"]
+    #[serde(default)]
+    synonyms: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    config: IndexConfig,
+    #[serde(skip)]
+    query_cache: QueryCache,
+    // Documents changed since the last `save_incremental`
+    #[serde(skip)]
+    dirty: DirtySet,
 }
 
-impl Index {
+// Reads `path_to_ids`, also accepting the single-id-per-path shape older
+// index files were written with.
+fn deserialize_path_ids<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<PathBuf, HashSet<DocumentId>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PathIds {
+        One(DocumentId),
+        Many(HashSet<DocumentId>),
+    }
+
+    let raw = HashMap::<PathBuf, PathIds>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(path, ids)| match ids {
+            PathIds::One(id) => (path, HashSet::from([id])),
+            PathIds::Many(ids) => (path, ids),
+        })
+        .collect())
+}
+
+// Settings picked at construction time through `IndexBuilder`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexConfig {
+    scoring: ScoringMode,
+    k1: f32,
+    b: f32,
+    #[serde(default)]
+    tokenizer: TokenizerOptions,
+    #[serde(default)]
+    order_by: OrderBy,
+    #[serde(default)]
+    max_query_terms: Option<usize>,
+    // Half-life for the optional recency boost in `search_ranked`
+    #[serde(default)]
+    recency_half_life: Option<Duration>,
+    #[serde(default)]
+    max_tokens_per_doc: Option<usize>,
+    // Lowercased language hint -> tokenizer strategy for documents
+    #[serde(default)]
+    languages: HashMap<String, TokenStrategy>,
+    #[serde(default)]
+    content_storage: ContentStorage,
+    #[serde(default)]
+    max_positions_per_term: Option<usize>,
+    // Entries kept by the `search_query` cache, 0 = off
+    #[serde(default)]
+    query_cache_capacity: usize,
+    // Ranking ignores terms found in more than this fraction of documents
+    #[serde(default)]
+    max_doc_frequency: Option<f32>,
+}
+
+/// Result of `Index::search_summary`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchSummary {
+    /// Documents matching at least one query term (OR)
+    pub any_count: usize,
+    /// Documents matching every query term (AND)
+    pub all_count: usize,
+    /// The AND matches themselves
+    pub all_ids: Vec<DocumentId>,
+}
+
+/// How search results are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderBy {
+    /// Unranked results by path. Ranked results by score, ties by path.
+    #[default]
+    PathAsc,
+    /// Unranked results newest `modified` first (unknown last), then path.
+    /// Ranked results by score, ties newest first.
+    ModifiedDesc,
+    /// Unranked results by how many distinct query terms they match, then
+    /// path. Ranked results by score, ties by path.
+    ScoreThenPath,
+}
+
+/// How much of each document's content the index keeps in memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ContentStorage {
+    /// Keep everything.
+    #[default]
+    Full,
+    /// Keep only the first `n` characters, enough for `preview`.
+    Preview(usize),
+    /// Keep nothing; postings are all a search-only deployment needs.
+    None,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        IndexConfig {
+            scoring: ScoringMode::Bm25,
+            k1: 1.2,
+            b: 0.75,
+            tokenizer: TokenizerOptions::default(),
+            order_by: OrderBy::default(),
+            max_query_terms: None,
+            recency_half_life: None,
+            max_tokens_per_doc: None,
+            languages: HashMap::new(),
+            content_storage: ContentStorage::default(),
+            max_positions_per_term: None,
+            query_cache_capacity: 0,
+            max_doc_frequency: None,
+        }
+    }
+}
+
+/// Configures an `Index` before it's created.
+///
+/// `Index::new()` is the same as `Index::builder().build()`.
+#[derive(Debug, Clone, Default)]
+pub struct IndexBuilder {
+    config: IndexConfig,
+}
+
+impl IndexBuilder {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// BM25 term-frequency saturation (`k1`, default 1.2) and length
+    /// normalization (`b`, default 0.75).
+    pub fn bm25(mut self, k1: f32, b: f32) -> Self {
+        self.config.k1 = k1;
+        self.config.b = b;
+        self
+    }
+
+    /// Which scorer `search_ranked` uses. Defaults to `ScoringMode::Bm25`.
+    pub fn scoring(mut self, mode: ScoringMode) -> Self {
+        self.config.scoring = mode;
+        self
+    }
+
+    /// Tokenizer settings used for both documents and queries.
+    pub fn tokenizer(mut self, options: TokenizerOptions) -> Self {
+        self.config.tokenizer = options;
+        self
+    }
+
+    /// Result ordering for the search methods. Defaults to `OrderBy::PathAsc`.
+    pub fn order_by(mut self, order_by: OrderBy) -> Self {
+        self.config.order_by = order_by;
+        self
+    }
+
+    /// Caps how many distinct terms of a query are looked up; anything past
+    /// the first `max` is ignored. Protects against pasted paragraphs turning
+    /// into hundreds of posting lookups. Unlimited by default.
+    pub fn max_query_terms(mut self, max: usize) -> Self {
+        self.config.max_query_terms = Some(max);
+        self
+    }
+
+    /// Boosts newer documents in `search_ranked`: a score is halved for
+    /// every `half_life` since the document was modified. Documents without
+    /// a `modified` time are left as is. Off by default.
+    pub fn recency_boost(mut self, half_life: Duration) -> Self {
+        self.config.recency_half_life = Some(half_life);
+        self
+    }
+
+    /// Indexes at most `max` distinct tokens per document (the first ones to
+    /// appear), so one huge file like a word list can't flood the index.
+    /// The full content is still stored. Unlimited by default.
+    pub fn max_tokens_per_doc(mut self, max: usize) -> Self {
+        self.config.max_tokens_per_doc = Some(max);
+        self
+    }
+
+    /// Tokenizes documents whose `lang` is `lang` (case-insensitive) with
+    /// `strategy`, e.g. `.language("ja", TokenStrategy::NGrams(2))`. Other
+    /// documents use plain word tokenization.
+    pub fn language(mut self, lang: &str, strategy: TokenStrategy) -> Self {
+        self.config.languages.insert(lang.to_lowercase(), strategy);
+        self
+    }
+
+    /// Stores at most the first `max` positions of each term per document,
+    /// so a page of "no no no no ..." can't bloat the positional data.
+    ///
+    /// Term frequencies (and so ranking) still count every occurrence, but
+    /// `search_near` only sees the kept positions: a match that needs a later
+    /// occurrence of a heavily repeated term is missed. Unlimited by default.
+    pub fn max_positions_per_term(mut self, max: usize) -> Self {
+        self.config.max_positions_per_term = Some(max);
+        self
+    }
+
+    /// How much content to keep per document. Anything less than
+    /// `ContentStorage::Full` saves memory and index size; `snippet` then
+    /// re-reads the file from disk. Defaults to `Full`.
+    pub fn content_storage(mut self, storage: ContentStorage) -> Self {
+        self.config.content_storage = storage;
+        self
+    }
+
+    /// Caches the results of up to `capacity` recent `search_query` calls,
+    /// for UIs that re-run the same queries. Any change to the index
+    /// invalidates the cache. Off by default.
+    pub fn query_cache(mut self, capacity: usize) -> Self {
+        self.config.query_cache_capacity = capacity;
+        self
+    }
+
+    /// Dynamic stop words: `search_ranked` ignores query terms that appear
+    /// in more than `fraction` (0 to 1) of all documents, so a word like
+    /// "note" that's in nearly every file adds nothing to any score.
+    ///
+    /// IDF already shrinks such terms close to zero; this drops them
+    /// outright. If every query term is that common they're all kept, so the
+    /// query still ranks something. Plain (unranked) search is unaffected.
+    pub fn max_doc_frequency(mut self, fraction: f32) -> Self {
+        self.config.max_doc_frequency = Some(fraction);
+        self
+    }
+
+    pub fn build(self) -> Index {
         Index {
-            postings: HashMap::new(),
+            version: INDEX_VERSION,
+            postings: BTreeMap::new(),
             documents: HashMap::new(),
-            path_to_id: HashMap::new(),
+            path_to_ids: HashMap::new(),
             doc_tokens: HashMap::new(),
+            term_freqs: HashMap::new(),
+            positions: HashMap::new(),
+            doc_lengths: HashMap::new(),
+            indexed_at: HashMap::new(),
+            synonyms: HashMap::new(),
+            config: self.config,
+            query_cache: QueryCache::default(),
+            dirty: DirtySet::default(),
+        }
+    }
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Index {
+    pub fn new() -> Self {
+        IndexBuilder::new().build()
+    }
+
+    pub fn builder() -> IndexBuilder {
+        IndexBuilder::new()
+    }
+
+    /// Shorthand for `Index::builder().query_cache(capacity).build()`.
+    pub fn with_query_cache(capacity: usize) -> Self {
+        IndexBuilder::new().query_cache(capacity).build()
+    }
+
+    /// Shorthand for `Index::builder().content_storage(storage).build()`.
+    pub fn with_content_storage(storage: ContentStorage) -> Self {
+        IndexBuilder::new().content_storage(storage).build()
+    }
+
+    /// A default index holding `docs`, i.e. `Index::new()` followed by
+    /// `add_documents`. Handy for tests and one-shot indexing.
+    pub fn from_documents(docs: Vec<Document>) -> Self {
+        let mut index = Index::new();
+        index.add_documents(docs);
+        index
+    }
+
+    /// Indexes `doc`. A document already indexed under the same id (e.g. a
+    /// re-ingested file with `LoadOptions::deterministic_ids`) is replaced,
+    /// so none of its old terms linger.
+    pub fn add_document(&mut self, mut doc: Document) {
+        // 0. One spelling per file, so `./a.md` and `a.md` share a key
+        doc.path = normalize_path(&doc.path);
+
+        if self.documents.contains_key(&doc.id) {
+            self.remove_document(doc.id);
         }
+
+        // 1. Tokenize (per the document's language) & count occurrences
+        let tokens = self.document_tokens(&doc);
+        let length = tokens.len();
+
+        let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, token) in tokens.into_iter().enumerate() {
+            positions.entry(token).or_default().push(position);
+        }
+
+        // 2. Update the token maps & inverted index
+        self.index_tokens(doc.id, positions, length);
+
+        // 3. Store document (trimmed per ContentStorage) & path mapping
+        self.path_to_ids
+            .entry(doc.path.clone())
+            .or_default()
+            .insert(doc.id);
+        let content = self.stored_content(doc.content);
+        self.documents.insert(doc.id, Document { content, ..doc });
     }
 
-    pub fn add_document(&mut self, doc: Document) {
-        // 1. Tokenize & dedupe
-        let tokens = tokenize(&doc.content);
-        let unique_tokens: HashSet<String> = tokens.into_iter().collect();
+    // What's left of `content` after applying the ContentStorage setting.
+    fn stored_content(&self, content: String) -> String {
+        match self.config.content_storage {
+            ContentStorage::Full => content,
+            ContentStorage::Preview(max_chars) => match content.char_indices().nth(max_chars) {
+                Some((cut, _)) => content[..cut].to_string(),
+                None => content,
+            },
+            ContentStorage::None => String::new(),
+        }
+    }
 
-        // 2. Store tokens per document
-        self.doc_tokens.insert(doc.id, unique_tokens.clone());
+    // A document's full content: the stored copy, or re-read from its file
+    // when the index doesn't keep it all. Falls back to whatever is stored
+    // if the file can't be read (or is a chunk of a larger file).
+    fn full_content(&self, id: DocumentId) -> Option<Cow<'_, str>> {
+        let doc = self.documents.get(&id)?;
 
-        // 3. Update inverted index
-        for token in unique_tokens {
-            self.postings
-                .entry(token)
-                .or_insert_with(HashSet::new)
-                .insert(doc.id);
+        if self.config.content_storage == ContentStorage::Full || doc.chunk.is_some() {
+            return Some(Cow::Borrowed(&doc.content));
+        }
+
+        match read_content(&doc.path) {
+            Ok(content) => Some(Cow::Owned(content)),
+            Err(_) => Some(Cow::Borrowed(&doc.content)),
+        }
+    }
+
+    // Word tokens, plus n-grams when the document's language asks for them.
+    fn document_tokens(&self, doc: &Document) -> Vec<String> {
+        let mut tokens = tokenize_with(&doc.content, &self.config.tokenizer);
+
+        if let Some(TokenStrategy::NGrams(n)) = doc
+            .lang
+            .as_ref()
+            .and_then(|lang| self.config.languages.get(&lang.to_lowercase()))
+        {
+            tokens.extend(tokenize_ngrams(&doc.content, *n));
+        }
+
+        tokens
+    }
+
+    /// Indexes a piece of text that doesn't live in a file (stdin, a pipe,
+    /// tests) and returns its new id.
+    ///
+    /// The document gets a synthetic `text:<id>` path so several of them never
+    /// collide in `path_to_ids`, and `modified` is `None`.
+    pub fn add_text(&mut self, content: &str) -> DocumentId {
+        self.add_text_from(content, DocumentSource::Memory)
+    }
+
+    /// `add_text`, recording where the text came from (e.g. `Stdin`).
+    pub fn add_text_from(&mut self, content: &str, source: DocumentSource) -> DocumentId {
+        let id = DocumentId::random();
+
+        self.add_document(Document {
+            id,
+            path: PathBuf::from(format!("text:{}", id)),
+            content: content.to_string(),
+            modified: None,
+            chunk: None,
+            source,
+            lang: None,
+        });
+
+        id
+    }
+
+    /// Adds many documents at once, e.g. when building an index from
+    /// `load_documents`.
+    ///
+    /// Behaves exactly like calling `add_document` for each one, but reserves
+    /// map capacity up front from the iterator's size hint so the maps don't
+    /// keep rehashing as they grow.
+    pub fn add_documents(&mut self, docs: impl IntoIterator<Item = Document>) {
+        let docs = docs.into_iter();
+
+        // 1. Reserve room for at least the documents we know are coming
+        let (expected, _) = docs.size_hint();
+        self.reserve(expected);
+
+        // 2. Same path as single inserts so results can't drift apart
+        for doc in docs {
+            self.add_document(doc);
+        }
+    }
+
+    /// Indexes `reader` as a document at `path` without ever holding its full
+    /// content in memory, returning the new document's id.
+    ///
+    /// Input is read in fixed-size buffers and tokenized as it arrives, so
+    /// this suits huge log-like files. The stored `Document` has empty
+    /// `content`; search and ranking work as usual.
+    pub fn add_document_streaming<R: Read>(
+        &mut self,
+        path: PathBuf,
+        mut reader: R,
+    ) -> io::Result<DocumentId> {
+        let path = normalize_path(&path);
+        let doc_id = DocumentId::random();
+        let mut stream = StreamTokenizer::with_options(self.config.tokenizer.clone());
+        let mut buffer = vec![0u8; STREAM_BUFFER_SIZE];
+
+        // 1. Record tokens chunk by chunk, the tokenizer carries partial words
+        let mut positions: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut length = 0;
+        let mut count = |token: String| {
+            positions.entry(token).or_default().push(length);
+            length += 1;
+        };
+
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            stream.feed(&buffer[..read], &mut count);
+        }
+        stream.finish(&mut count);
+
+        // 2. Index exactly like add_document, minus the content
+        self.index_tokens(doc_id, positions, length);
+
+        let doc = Document {
+            id: doc_id,
+            ..Document::file(path, String::new(), None)
+        };
+        self.path_to_ids
+            .entry(doc.path.clone())
+            .or_default()
+            .insert(doc_id);
+        self.documents.insert(doc_id, doc);
+
+        Ok(doc_id)
+    }
+
+    // Records where each token occurs in a document, filling doc_tokens,
+    // term_freqs, positions, doc_lengths and postings.
+    fn index_tokens(
+        &mut self,
+        doc_id: DocumentId,
+        mut positions: HashMap<String, Vec<usize>>,
+        length: usize,
+    ) {
+        self.query_cache.invalidate();
+        self.dirty.mark(doc_id);
+
+        // 0. Keep only the first `max_tokens_per_doc` distinct tokens, in
+        //    order of first appearance
+        if let Some(max) = self.config.max_tokens_per_doc
+            && positions.len() > max
+        {
+            let mut first_seen: Vec<usize> = positions.values().map(|at| at[0]).collect();
+            first_seen.sort_unstable();
+            let cutoff = first_seen[max];
+            positions.retain(|_, at| at[0] < cutoff);
+        }
+
+        // 1. Store (deduped) tokens & counts per document
+        let unique_tokens: HashSet<String> = positions.keys().cloned().collect();
+        let freqs = positions
+            .iter()
+            .map(|(token, at)| (token.clone(), at.len()))
+            .collect();
+
+        // Counted above in full, only the positions get capped
+        if let Some(max) = self.config.max_positions_per_term {
+            for at in positions.values_mut() {
+                at.truncate(max);
+            }
         }
 
-        // 4. Store document & path mapping
-        self.documents.insert(doc.id, doc.clone());
-        self.path_to_id.insert(doc.path.clone(), doc.id);
+        self.doc_tokens.insert(doc_id, unique_tokens.clone());
+        self.term_freqs.insert(doc_id, freqs);
+        self.positions.insert(doc_id, positions);
+        self.doc_lengths.insert(doc_id, length);
+        self.indexed_at.insert(doc_id, SystemTime::now());
+
+        // 2. Update inverted index
+        for token in unique_tokens {
+            self.postings.entry(token).or_default().insert(doc_id);
+        }
     }
 
-    pub fn remove_document(&mut self, doc_id: Uuid) -> () {
-        if let Some(doc) = self.documents.get(&doc_id) {
-            let path = doc.path.clone();
-            self.path_to_id.remove(&path);
+    pub fn remove_document(&mut self, doc_id: DocumentId) {
+        self.query_cache.invalidate();
+        self.dirty.mark(doc_id);
+
+        // Only this document's entry; others sharing the path stay reachable
+        if let Some(doc) = self.documents.get(&doc_id)
+            && let Some(ids) = self.path_to_ids.get_mut(&doc.path)
+        {
+            ids.remove(&doc_id);
+            if ids.is_empty() {
+                self.path_to_ids.remove(&doc.path);
+            }
         }
 
         let tokens = match self.doc_tokens.get(&doc_id) {
@@ -71,129 +632,1148 @@ impl Index {
         }
 
         self.doc_tokens.remove(&doc_id);
+        self.term_freqs.remove(&doc_id);
+        self.positions.remove(&doc_id);
+        self.doc_lengths.remove(&doc_id);
+        self.indexed_at.remove(&doc_id);
         self.documents.remove(&doc_id);
     }
 
-    pub fn search_query(&self, query: &str) -> Vec<Uuid> {
-        // 1. Tokenize the query
-        let tokens = tokenize(query);
+    /// Returns every document containing at least one query token.
+    ///
+    /// Results follow the index's `OrderBy` (by path unless configured
+    /// otherwise), always ending with the id as a last resort, so the same
+    /// index and query produce the same sequence.
+    pub fn search_query(&self, query: &str) -> Vec<DocumentId> {
+        // 1. Tokenize the query (plus any synonyms)
+        let tokens = self.query_tokens(query);
+
+        // 1b. Same token set as a recent query? Reuse its results
+        let capacity = self.config.query_cache_capacity;
+        let cache_key = (capacity > 0).then(|| {
+            let mut key = tokens.clone();
+            key.sort();
+            key.join(" ")
+        });
+        if let Some(cached) = cache_key
+            .as_deref()
+            .and_then(|key| self.query_cache.get(key))
+        {
+            return cached;
+        }
 
         // 2. Create empty SET of doc ids
         let mut doc_ids = HashSet::new();
 
         // 3. Loop over tokens - if tokens exist in postings, add all doc ids to set
-        for token in tokens {
-            if let Some(ids) = self.postings.get(&token) {
+        for token in &tokens {
+            if let Some(ids) = self.postings.get(token) {
                 for uuid in ids {
                     // Deref here otherwise it will try to insert &uuid
-                    // but we want doc ids to contain/return using owned Uuid
+                    // but we want doc ids to contain/return using owned DocumentId
                     doc_ids.insert(*uuid);
                 }
             }
         }
 
-        // 4. conovert and return SET as a Vec<Uuid> like the sig expects
-        doc_ids.into_iter().collect()
+        // 4. conovert SET to a Vec<DocumentId> like the sig expects
+        let mut results: Vec<DocumentId> = doc_ids.into_iter().collect();
+
+        // 5. HashSet order is random per run, so give callers a stable order
+        self.order_results(&mut results, &tokens);
+
+        if let Some(key) = cache_key {
+            self.query_cache.insert(key, results.clone(), capacity);
+        }
+
+        results
+    }
+
+    /// Lazily yields the ids of documents containing `token` (normalized
+    /// like indexed text), in ascending id order, without collecting them.
+    /// Empty for unknown terms.
+    pub fn posting_iter<'a>(&'a self, token: &str) -> impl Iterator<Item = DocumentId> + 'a {
+        self.first_token(token)
+            .and_then(|token| self.postings.get(&token))
+            .into_iter()
+            .flatten()
+            .copied()
+    }
+
+    /// Calls `f` once for every document `search_query` would return,
+    /// as they're found, without building the result list. Handy for
+    /// streaming results out or just counting them.
+    ///
+    /// Ids come in posting order (term by term), not `search_query`'s
+    /// order, and bypass the query cache.
+    pub fn search_for_each<F: FnMut(DocumentId)>(&self, query: &str, mut f: F) {
+        let tokens = self.query_tokens(query);
+
+        // A single term's posting list has no duplicates, so skip the
+        // bookkeeping entirely
+        if let [token] = tokens.as_slice() {
+            self.postings
+                .get(token)
+                .into_iter()
+                .flatten()
+                .for_each(|id| f(*id));
+            return;
+        }
+
+        let mut seen = HashSet::new();
+        for token in &tokens {
+            for id in self.postings.get(token).into_iter().flatten() {
+                if seen.insert(*id) {
+                    f(*id);
+                }
+            }
+        }
+    }
+
+    /// `search_query` limited to `candidates`, e.g. the survivors of a cheap
+    /// filter computed by the caller. Ordered like `search_query`.
+    pub fn search_within(&self, query: &str, candidates: &HashSet<DocumentId>) -> Vec<DocumentId> {
+        let mut results = self.search_query(query);
+        results.retain(|id| candidates.contains(id));
+        results
+    }
+
+    /// `search_query` minus every document containing any token of
+    /// `exclude`, i.e. "matches X but not Y". Ordered like `search_query`.
+    ///
+    /// Exclusions are matched literally: synonyms aren't expanded, so
+    /// excluding "car" doesn't also drop documents about "automobile".
+    pub fn search_excluding(&self, query: &str, exclude: &str) -> Vec<DocumentId> {
+        let excluded: HashSet<DocumentId> = tokenize_with(exclude, &self.config.tokenizer)
+            .iter()
+            .filter_map(|token| self.postings.get(token))
+            .flatten()
+            .copied()
+            .collect();
+
+        let mut results = self.search_query(query);
+        results.retain(|id| !excluded.contains(id));
+        results
+    }
+
+    /// `search_query` restricted to documents from one `DocumentSource`.
+    pub fn search_by_source(&self, query: &str, source: DocumentSource) -> Vec<DocumentId> {
+        let mut results = self.search_query(query);
+        results.retain(|id| {
+            self.documents
+                .get(id)
+                .is_some_and(|doc| doc.source == source)
+        });
+        results
+    }
+
+    /// When a document was last added or re-indexed, which can be much later
+    /// than its `modified` time. `None` for unknown ids and for documents
+    /// loaded from an index saved before this was tracked.
+    pub fn indexed_at(&self, id: DocumentId) -> Option<SystemTime> {
+        self.indexed_at.get(&id).copied()
+    }
+
+    /// A stored document by id.
+    pub fn document(&self, id: DocumentId) -> Option<&Document> {
+        self.documents.get(&id)
+    }
+
+    /// The distinct terms a document contributed to the index (e.g. for a
+    /// tag cloud), or `None` for unknown ids.
+    pub fn terms_for_document(&self, id: DocumentId) -> Option<&HashSet<String>> {
+        self.doc_tokens.get(&id)
+    }
+
+    /// Every indexed path, sorted, without touching document content.
+    ///
+    /// Text that never came from a file (`add_text`, stdin) has a synthetic
+    /// `text:<id>` path and is left out. Chunks show up under their own
+    /// `<file>#<offset>` paths.
+    pub fn document_paths(&self) -> Vec<&PathBuf> {
+        let mut paths: Vec<&PathBuf> = self
+            .path_to_ids
+            .iter()
+            .filter(|(path, ids)| {
+                let synthetic = ids
+                    .iter()
+                    .filter_map(|id| self.documents.get(id))
+                    .any(|doc| {
+                        matches!(doc.source, DocumentSource::Memory | DocumentSource::Stdin)
+                    });
+                !synthetic && !path.as_os_str().is_empty()
+            })
+            .map(|(path, _)| path)
+            .collect();
+
+        paths.sort();
+        paths
+    }
+
+    /// Number of documents currently in the index.
+    pub fn document_count(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Whether the index holds no documents at all.
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Tokenizer settings this index applies to documents and queries.
+    pub fn tokenizer_options(&self) -> &TokenizerOptions {
+        &self.config.tokenizer
+    }
+
+    /// Every indexed term with its total number of occurrences across all
+    /// documents, most frequent first (ties alphabetical). Handy for spotting
+    /// noise terms worth adding to the stop words.
+    pub fn token_histogram(&self) -> Vec<(String, usize)> {
+        // 1. Sum the per-document term frequencies
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for freqs in self.term_freqs.values() {
+            for (term, count) in freqs {
+                *totals.entry(term.as_str()).or_default() += count;
+            }
+        }
+
+        // 2. Most frequent first
+        let mut histogram: Vec<(String, usize)> = totals
+            .into_iter()
+            .map(|(term, count)| (term.to_string(), count))
+            .collect();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        histogram
+    }
+
+    /// First `max_chars` characters of a document's content, with a trailing
+    /// `…` when anything was cut off. Counting chars (not bytes) means
+    /// multi-byte text is never split mid-character.
+    pub fn preview(&self, id: DocumentId, max_chars: usize) -> Option<String> {
+        let content = &self.documents.get(&id)?.content;
+
+        // Byte index of the first char that doesn't fit, if there is one
+        match content.char_indices().nth(max_chars) {
+            Some((cut, _)) => Some(format!("{}…", &content[..cut])),
+            None => Some(content.clone()),
+        }
+    }
+
+    /// Length of a document's stored content in bytes, without copying it.
+    pub fn content_len(&self, id: DocumentId) -> Option<usize> {
+        self.documents.get(&id).map(|doc| doc.content.len())
+    }
+
+    /// Replaces the synonym map used to expand queries.
+    ///
+    /// A query token equal to a key also searches for each of its synonyms.
+    /// Expansion is one-directional and happens only at query time, so the
+    /// postings stay untouched. Keys and synonyms are lowercased to match
+    /// tokenizer output.
+    pub fn set_synonyms(&mut self, synonyms: HashMap<String, Vec<String>>) {
+        self.query_cache.invalidate();
+        self.synonyms = synonyms
+            .into_iter()
+            .map(|(term, alternatives)| {
+                let alternatives = alternatives.iter().map(|a| a.to_lowercase()).collect();
+                (term.to_lowercase(), alternatives)
+            })
+            .collect();
+    }
+
+    // Tokens to look up for `query`: each query token followed by its
+    // synonyms, without duplicates.
+    fn query_tokens(&self, query: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+
+        self.query_term_groups(query)
+            .into_iter()
+            .flatten()
+            .filter(|token| seen.insert(token.clone()))
+            .collect()
+    }
+
+    // Queries don't know their language, so they get word tokens plus the
+    // n-grams of every n-gram language configured. Plain ASCII queries have
+    // no n-grams and fall back to words alone.
+    fn tokenize_query(&self, query: &str) -> Vec<String> {
+        let mut tokens = tokenize_with(query, &self.config.tokenizer);
+
+        let mut sizes: Vec<usize> = self
+            .config
+            .languages
+            .values()
+            .filter_map(|strategy| match strategy {
+                TokenStrategy::NGrams(n) => Some(*n),
+                TokenStrategy::Words => None,
+            })
+            .collect();
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        for n in sizes {
+            tokens.extend(tokenize_ngrams(query, n));
+        }
+
+        tokens
+    }
+
+    // One group per distinct query term: the term itself followed by its
+    // synonyms. Only the first `max_query_terms` distinct terms are kept
+    // (synonyms don't count towards the limit).
+    fn query_term_groups(&self, query: &str) -> Vec<Vec<String>> {
+        let mut seen = HashSet::new();
+        let mut groups = Vec::new();
+
+        for token in self.tokenize_query(query) {
+            if seen.contains(&token) {
+                continue;
+            }
+
+            if let Some(max) = self.config.max_query_terms
+                && seen.len() >= max
+            {
+                break;
+            }
+            seen.insert(token.clone());
+
+            let synonyms = self.synonyms.get(&token).cloned().unwrap_or_default();
+            groups.push(std::iter::once(token).chain(synonyms).collect());
+        }
+
+        groups
+    }
+
+    /// Returns every document containing all query terms (AND), ordered
+    /// like `search_query`. A term matches through its synonyms too.
+    pub fn search_all(&self, query: &str) -> Vec<DocumentId> {
+        self.search_summary(query).all_ids
+    }
+
+    /// Counts for a search UI ("12 match any term, 3 match all") computed in
+    /// one pass over the query's posting lists.
+    ///
+    /// A document matches a term if it contains the term or one of its
+    /// synonyms. `all_ids` is ordered like `search_query`.
+    pub fn search_summary(&self, query: &str) -> SearchSummary {
+        let groups = self.query_term_groups(query);
+
+        // 1. How many distinct query terms each document matched
+        let mut matched_terms: HashMap<DocumentId, usize> = HashMap::new();
+
+        for group in &groups {
+            let group_docs: HashSet<DocumentId> = group
+                .iter()
+                .filter_map(|token| self.postings.get(token))
+                .flatten()
+                .copied()
+                .collect();
+
+            for doc_id in group_docs {
+                *matched_terms.entry(doc_id).or_default() += 1;
+            }
+        }
+
+        // 2. OR = any count, AND = matched every term
+        let mut all_ids: Vec<DocumentId> = matched_terms
+            .iter()
+            .filter(|(_, count)| **count == groups.len())
+            .map(|(doc_id, _)| *doc_id)
+            .collect();
+
+        let tokens: Vec<String> = groups.into_iter().flatten().collect();
+        self.order_results(&mut all_ids, &tokens);
+
+        SearchSummary {
+            any_count: matched_terms.len(),
+            all_count: all_ids.len(),
+            all_ids,
+        }
+    }
+
+    /// Like `search_query`, but returns at most one hit per file.
+    ///
+    /// Chunks are grouped by their parent path and plain documents by their
+    /// own path. Within a group the document matching the most distinct query
+    /// tokens wins, ties going to the first one in `search_query` order.
+    pub fn search_dedup_by_path(&self, query: &str) -> Vec<DocumentId> {
+        let query_tokens: HashSet<String> = self.query_tokens(query).into_iter().collect();
+
+        // parent path -> (best doc id, its score)
+        let mut best: HashMap<&Path, (DocumentId, usize)> = HashMap::new();
+
+        for doc_id in self.search_query(query) {
+            let Some(doc) = self.documents.get(&doc_id) else {
+                continue;
+            };
+
+            let parent = match &doc.chunk {
+                Some(info) => info.parent_path.as_path(),
+                None => doc.path.as_path(),
+            };
+
+            let score = self
+                .doc_tokens
+                .get(&doc_id)
+                .map(|tokens| query_tokens.iter().filter(|t| tokens.contains(*t)).count())
+                .unwrap_or(0);
+
+            best.entry(parent)
+                .and_modify(|current| {
+                    if score > current.1 {
+                        *current = (doc_id, score);
+                    }
+                })
+                .or_insert((doc_id, score));
+        }
+
+        let mut results: Vec<DocumentId> = best.into_values().map(|(doc_id, _)| doc_id).collect();
+        let query_tokens: Vec<String> = query_tokens.into_iter().collect();
+        self.order_results(&mut results, &query_tokens);
+
+        results
     }
 
-    pub fn remove_document_by_path(&mut self, path: &PathBuf) {
-        if let Some(doc_id) = self.path_to_id.get(path).copied() {
+    /// `search_query` results bucketed by parent directory, e.g. for a file
+    /// tree UI. Each bucket keeps `search_query` order; documents without a
+    /// parent (like a bare `/`) go under the empty path.
+    pub fn search_grouped(&self, query: &str) -> HashMap<PathBuf, Vec<DocumentId>> {
+        let mut groups: HashMap<PathBuf, Vec<DocumentId>> = HashMap::new();
+
+        for doc_id in self.search_query(query) {
+            let Some(doc) = self.documents.get(&doc_id) else {
+                continue;
+            };
+
+            let parent = doc.path.parent().unwrap_or(Path::new(""));
+            groups.entry(parent.to_path_buf()).or_default().push(doc_id);
+        }
+
+        groups
+    }
+
+    // Sorts ids by their document path, falling back to the id itself so
+    // documents sharing a path still have a fixed order.
+    fn sort_by_path(&self, ids: &mut [DocumentId]) {
+        ids.sort_by(|a, b| self.compare_path(a, b));
+    }
+
+    fn compare_path(&self, a: &DocumentId, b: &DocumentId) -> Ordering {
+        let path_a = self.documents.get(a).map(|doc| &doc.path);
+        let path_b = self.documents.get(b).map(|doc| &doc.path);
+
+        path_a.cmp(&path_b).then_with(|| a.cmp(b))
+    }
+
+    // Secondary order used wherever the primary criterion ties.
+    fn compare_tiebreak(&self, a: &DocumentId, b: &DocumentId) -> Ordering {
+        match self.config.order_by {
+            OrderBy::ModifiedDesc => {
+                let modified_a = self.documents.get(a).and_then(|doc| doc.modified);
+                let modified_b = self.documents.get(b).and_then(|doc| doc.modified);
+
+                // Reversed, so newest first and `None` (unknown) last
+                modified_b
+                    .cmp(&modified_a)
+                    .then_with(|| self.compare_path(a, b))
+            }
+            OrderBy::PathAsc | OrderBy::ScoreThenPath => self.compare_path(a, b),
+        }
+    }
+
+    // Orders unranked results according to the configured `OrderBy`.
+    // `ScoreThenPath` scores each document by how many distinct query tokens
+    // it contains.
+    fn order_results(&self, ids: &mut [DocumentId], query_tokens: &[String]) {
+        if self.config.order_by != OrderBy::ScoreThenPath {
+            ids.sort_by(|a, b| self.compare_tiebreak(a, b));
+            return;
+        }
+
+        let matched = |doc_id: &DocumentId| {
+            self.doc_tokens.get(doc_id).map_or(0, |tokens| {
+                query_tokens.iter().filter(|t| tokens.contains(*t)).count()
+            })
+        };
+
+        ids.sort_by(|a, b| {
+            matched(b)
+                .cmp(&matched(a))
+                .then_with(|| self.compare_path(a, b))
+        });
+    }
+
+    /// Keeps only the documents for which `keep` returns true, removing the
+    /// rest exactly like `remove_document` (postings and all derived maps).
+    pub fn retain_documents<F: FnMut(&Document) -> bool>(&mut self, mut keep: F) {
+        let doomed: Vec<DocumentId> = self
+            .documents
+            .values()
+            .filter(|doc| !keep(doc))
+            .map(|doc| doc.id)
+            .collect();
+
+        for doc_id in doomed {
             self.remove_document(doc_id);
         }
     }
 
+    /// Removes every file-sourced document whose file no longer exists,
+    /// returning how many were removed. Chunks are checked against their
+    /// parent file; stdin, in-memory and imported documents are left alone.
+    ///
+    /// A quick sweep for vanished files, unlike `reconcile` it doesn't look
+    /// for new or changed ones.
+    pub fn remove_orphan_documents(&mut self) -> usize {
+        let before = self.documents.len();
+
+        self.retain_documents(|doc| {
+            let file = doc
+                .chunk
+                .as_ref()
+                .map_or(&doc.path, |info| &info.parent_path);
+
+            doc.source != DocumentSource::File || file.exists()
+        });
+
+        before - self.documents.len()
+    }
+
+    /// Every document stored under `path`, sorted. Usually one, but
+    /// `add_document` doesn't dedupe by path.
+    ///
+    /// Paths are compared after `normalize_path`, so any spelling of the
+    /// same file finds it.
+    pub fn ids_for_path(&self, path: &Path) -> Vec<DocumentId> {
+        let mut ids: Vec<DocumentId> = self
+            .path_to_ids
+            .get(&normalize_path(path))
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default();
+        ids.sort();
+        ids
+    }
+
+    /// The document stored under `path`, the lowest id if several share it.
+    pub fn id_for_path(&self, path: &Path) -> Option<DocumentId> {
+        self.path_to_ids
+            .get(&normalize_path(path))?
+            .iter()
+            .min()
+            .copied()
+    }
+
+    /// Removes every document stored under `path`, returning whether there
+    /// was any.
+    pub fn remove_document_by_path(&mut self, path: &Path) -> bool {
+        self.remove_path(path) > 0
+    }
+
+    /// `remove_document_by_path` for many paths at once. Returns how many
+    /// documents were actually removed; unknown paths are skipped.
+    pub fn remove_documents_by_paths(&mut self, paths: &[PathBuf]) -> usize {
+        paths.iter().map(|path| self.remove_path(path)).sum()
+    }
+
+    // Removes every document at `path`, returning how many there were.
+    fn remove_path(&mut self, path: &Path) -> usize {
+        let doc_ids = self.ids_for_path(path);
+
+        for doc_id in &doc_ids {
+            self.remove_document(*doc_id);
+        }
+
+        doc_ids.len()
+    }
+
+    /// Moves every document stored under `from` to `to` without
+    /// re-tokenizing anything, for a file that was renamed but not edited.
+    /// Returns whether there was a document at `from`.
+    ///
+    /// Like a rename on disk, documents already at `to` are replaced.
+    pub fn rename_document(&mut self, from: &Path, to: &Path) -> bool {
+        let (from, to) = (normalize_path(from), normalize_path(to));
+        let Some(ids) = self.path_to_ids.remove(&from) else {
+            return false;
+        };
+
+        if from == to {
+            self.path_to_ids.insert(from, ids);
+            return true;
+        }
+
+        // 1. Whatever used to live at `to` is gone now
+        self.remove_path(&to);
+
+        // 2. Point the moved documents (and their chunk info) at the new path
+        for id in &ids {
+            if let Some(doc) = self.documents.get_mut(id) {
+                doc.path = to.clone();
+                if let Some(chunk) = doc.chunk.as_mut().filter(|c| c.parent_path == from) {
+                    chunk.parent_path = to.clone();
+                }
+            }
+            self.dirty.mark(*id);
+        }
+        self.path_to_ids.insert(to, ids);
+
+        // 3. Results are ordered by path, so cached ones may be out of order
+        self.query_cache.invalidate();
+
+        true
+    }
+
+    /// Adds `doc`, first removing every document already at its path.
     pub fn upsert_document(&mut self, doc: Document) {
-        // If a document already exists for this path, remove it first
-        if let Some(existing_id) = self.path_to_id.get(&doc.path).copied() {
+        for existing_id in self.ids_for_path(&doc.path) {
             self.remove_document(existing_id);
         }
 
         self.add_document(doc);
     }
 
-    pub fn save_to_disk<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
-        // Walk entire index and convert to json
-        let json = serde_json::to_string_pretty(self).expect("Index shouold serialize");
+    /// Reads the file at `path` (like `load_document`) and upserts it,
+    /// returning its id. A path that's already indexed keeps its id, so this
+    /// is all a watcher needs on a create or modify event.
+    pub fn upsert_path(&mut self, path: &Path) -> Result<DocumentId, IngestError> {
+        let doc = load_document(path)?;
+        let id = self.id_for_path(path).unwrap_or(doc.id);
+
+        self.upsert_document(Document { id, ..doc });
+        Ok(id)
+    }
+
+    pub fn save_to_disk<P: AsRef<Path>>(&self, path: P) -> Result<(), IndexError> {
+        // Walk entire index and convert to json (e.g. non UTF-8 paths can fail here)
+        let json = serde_json::to_string_pretty(self)?;
 
         // write file and handle Result
         fs::write(path, json)?;
         Ok(())
     }
 
-    // Returns fully constructed Index (Self) OR an IO error
-    pub fn load_from_disk<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let json = fs::read_to_string(path)?;
-        let index = serde_json::from_str(&json).expect("Index should deserialize");
+    /// `save_to_disk` without the pretty-printing whitespace, for production
+    /// indexes where file size matters more than reading it by eye (often
+    /// around half the size). `load_from_disk` reads both.
+    pub fn save_to_disk_compact<P: AsRef<Path>>(&self, path: P) -> Result<(), IndexError> {
+        // Serialized straight to bytes; nothing is written if this fails
+        let json = serde_json::to_vec(self)?;
+
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    // Returns fully constructed Index (Self) OR an IO/serde error. Files
+    // from another format version are rejected before anything else is read.
+    pub fn load_from_disk<P: AsRef<Path>>(path: P) -> Result<Self, IndexError> {
+        #[derive(Deserialize)]
+        struct Header {
+            #[serde(default = "unversioned")]
+            version: u32,
+        }
+
+        let json = fs::read_to_string(path.as_ref())?;
+
+        // 1. Only the version first, so an incompatible file gets a clear
+        //    error instead of whatever serde trips over
+        let header: Header = serde_json::from_str(&json)?;
+        if header.version != INDEX_VERSION {
+            return Err(IndexError::VersionMismatch {
+                found: header.version,
+                expected: INDEX_VERSION,
+            });
+        }
+
+        // 2. Then the whole index, which now matches the file exactly
+        let mut index: Index = serde_json::from_str(&json)?;
+        index.dirty.synced(path.as_ref());
 
         Ok(index)
     }
+
+    /// Modified time of the index file at `path`, for noticing when another
+    /// process saved over it.
+    pub fn disk_version<P: AsRef<Path>>(path: P) -> io::Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    /// Replaces `self` with the index at `path` if the file is newer than
+    /// `last` (a previous `disk_version`).
+    ///
+    /// Returns the new version when it reloaded, so callers can keep it as
+    /// their next `last`, and `None` when the file hasn't changed. On error
+    /// `self` is left as it was.
+    pub fn reload_if_changed<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        last: SystemTime,
+    ) -> Result<Option<SystemTime>, IndexError> {
+        let path = path.as_ref();
+        let current = Self::disk_version(path)?;
+
+        if current <= last {
+            return Ok(None);
+        }
+
+        *self = Self::load_from_disk(path)?;
+        Ok(Some(current))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ingestion::Document;
+    use crate::ingestion::{Document, test_doc, test_doc_modified};
     use std::path::PathBuf;
+    use uuid::Uuid;
 
     #[test]
     fn new_index_is_empty() {
         let index = Index::new();
         assert!(index.postings.is_empty());
+        assert!(index.is_empty());
+        assert_eq!(index.document_count(), 0);
+    }
+
+    #[test]
+    fn populated_index_is_not_empty() {
+        let mut index = Index::new();
+        index.add_text("hello world");
+
+        assert!(!index.is_empty());
+        assert_eq!(index.document_count(), 1);
     }
 
     #[test]
     fn add_single_document_indexes_tokens() {
         let mut index = Index::new();
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Hello world".to_string(),
-            modified: None,
-        };
+        let doc = test_doc("note.txt", "Hello world");
 
         // Extract what we need before move so we can still assert
         let doc_id = doc.id;
 
         index.add_document(doc);
 
-        assert!(index.postings["hello"].contains(&doc_id));
-        assert!(index.postings["world"].contains(&doc_id));
+        assert!(index.postings["hello"].contains(&doc_id));
+        assert!(index.postings["world"].contains(&doc_id));
+
+        assert_eq!(index.postings["hello"].len(), 1);
+        assert_eq!(index.postings["world"].len(), 1);
+    }
+
+    #[test]
+    fn add_two_docs() {
+        let mut index = Index::new();
+
+        let doc = test_doc("note.txt", "Hello world");
+
+        let doc2 = test_doc("note.txt", "Hello world how are you friend?");
+
+        // Grab ids before moving doc ownership
+        let doc_id = doc.id;
+        let doc_id_2 = doc2.id;
+
+        index.add_document(doc);
+        index.add_document(doc2);
+
+        assert!(index.postings["hello"].contains(&doc_id));
+        assert!(index.postings["friend"].contains(&doc_id_2));
+
+        assert_eq!(index.postings["hello"].len(), 2);
+        assert_eq!(index.postings["world"].len(), 2);
+    }
+
+    #[test]
+    fn empty_and_whitespace_documents_are_stored_without_postings() {
+        let mut index = Index::new();
+
+        let empty = test_doc("empty.txt", "");
+
+        let blank = test_doc("blank.txt", "  \n\t \n");
+
+        let empty_id = empty.id;
+        let blank_id = blank.id;
+
+        index.add_document(empty);
+        index.add_document(blank);
+
+        // Both docs are kept, they just contribute nothing to postings
+        assert!(index.documents.contains_key(&empty_id));
+        assert!(index.documents.contains_key(&blank_id));
+        assert!(index.doc_tokens[&empty_id].is_empty());
+        assert!(index.doc_tokens[&blank_id].is_empty());
+        assert!(index.postings.is_empty());
+
+        // Nothing to find and nothing to trip over
+        assert!(index.search_query("anything").is_empty());
+        assert!(index.search_query("").is_empty());
+
+        // Removing them still cleans up every map
+        index.remove_document(empty_id);
+        index.remove_document_by_path(&PathBuf::from("blank.txt"));
+        assert!(index.documents.is_empty());
+        assert!(index.doc_tokens.is_empty());
+        assert!(index.path_to_ids.is_empty());
+    }
+
+    #[test]
+    fn remove_documents_by_paths_counts_only_present_paths() {
+        let mut index = Index::new();
+        for path in ["a.md", "b.md", "c.md"] {
+            index.add_document(test_doc(path, "shared"));
+        }
+
+        let removed = index.remove_documents_by_paths(&[
+            PathBuf::from("a.md"),
+            PathBuf::from("missing.md"),
+            PathBuf::from("c.md"),
+            PathBuf::from("a.md"),
+        ]);
+
+        assert_eq!(removed, 2);
+        assert_eq!(index.document_count(), 1);
+        assert!(index.path_to_ids.contains_key(&PathBuf::from("b.md")));
+        assert_eq!(index.verify(), Ok(()));
+    }
+
+    #[test]
+    fn add_documents_matches_adding_one_by_one() {
+        let docs: Vec<Document> = (0..50)
+            .map(|i| {
+                test_doc(
+                    format!("note_{}.md", i),
+                    &format!("shared words plus unique{} and {}", i, i % 7),
+                )
+            })
+            .collect();
+
+        let mut looped = Index::new();
+        for doc in docs.iter().cloned() {
+            looped.add_document(doc);
+        }
+
+        let mut bulk = Index::new();
+        bulk.add_documents(docs.clone());
+
+        // Each build stamps its own indexing time, everything else must match
+        looped.indexed_at = bulk.indexed_at.clone();
+        assert_eq!(bulk, looped);
+        assert!(bulk.documents.capacity() >= docs.len());
+        assert_eq!(bulk.search_query("unique7"), looped.search_query("unique7"));
+    }
+
+    #[test]
+    fn preview_truncates_on_char_boundaries() {
+        let mut index = Index::new();
+
+        let doc = test_doc("cafe.md", "café crème brûlée");
+        let doc_id = doc.id;
+        index.add_document(doc);
+
+        // Cut right after the multi-byte 'é' and right before 'è'
+        assert_eq!(index.preview(doc_id, 4), Some("café…".to_string()));
+        assert_eq!(index.preview(doc_id, 7), Some("café cr…".to_string()));
+
+        // Long enough limits return the content untouched
+        assert_eq!(
+            index.preview(doc_id, 17),
+            Some("café crème brûlée".to_string())
+        );
+        assert_eq!(
+            index.preview(doc_id, 100),
+            Some("café crème brûlée".to_string())
+        );
+        assert_eq!(index.preview(doc_id, 0), Some("…".to_string()));
+
+        // Bytes, not chars: 17 chars but 4 of them take two bytes
+        assert_eq!(index.content_len(doc_id), Some(21));
+
+        let unknown = DocumentId::random();
+        assert_eq!(index.preview(unknown, 10), None);
+        assert_eq!(index.content_len(unknown), None);
+    }
+
+    #[test]
+    fn index_uses_configured_tokenizer_for_documents_and_queries() {
+        use crate::tokenizer::ApostropheMode;
+
+        let mut index = Index::builder()
+            .tokenizer(TokenizerOptions {
+                apostrophe: ApostropheMode::Strip,
+                ..TokenizerOptions::default()
+            })
+            .build();
+
+        let doc = test_doc("note.txt", "I don't know");
+        let doc_id = doc.id;
+        index.add_document(doc);
+
+        assert!(index.postings.contains_key("dont"));
+        assert!(!index.postings.contains_key("t"));
+        assert_eq!(index.search_query("DON'T"), vec![doc_id]);
+        assert_eq!(index.search_query("dont"), vec![doc_id]);
+    }
+
+    #[test]
+    fn max_query_terms_uses_only_the_first_terms() {
+        let mut index = Index::builder().max_query_terms(2).build();
+
+        let ids: Vec<DocumentId> = ["alpha", "beta", "gamma"]
+            .iter()
+            .map(|word| index.add_text(word))
+            .collect();
+
+        // Below and at the limit everything is searched
+        assert_eq!(index.search_query("alpha").len(), 1);
+        assert_eq!(index.search_query("alpha beta").len(), 2);
+
+        // Repeats don't use up the budget
+        assert_eq!(index.search_query("alpha alpha beta").len(), 2);
+
+        // Above the limit the extra terms are dropped
+        let results = index.search_query("alpha beta gamma");
+        assert_eq!(results.len(), 2);
+        assert!(!results.contains(&ids[2]));
+        assert_eq!(index.search_ranked("alpha beta gamma").len(), 2);
+
+        // Unlimited by default
+        let mut unlimited = Index::new();
+        for word in ["alpha", "beta", "gamma"] {
+            unlimited.add_text(word);
+        }
+        assert_eq!(unlimited.search_query("alpha beta gamma").len(), 3);
+    }
+
+    #[test]
+    fn max_tokens_per_doc_keeps_the_first_distinct_tokens() {
+        let mut index = Index::builder().max_tokens_per_doc(3).build();
+
+        let id = index.add_text("alpha beta alpha gamma delta epsilon beta");
+
+        let mut indexed: Vec<&String> = index.postings.keys().collect();
+        indexed.sort();
+        assert_eq!(indexed, vec!["alpha", "beta", "gamma"]);
+        assert_eq!(index.doc_tokens[&id].len(), 3);
+
+        // Repeats of kept tokens still count, the content is kept whole
+        assert_eq!(index.term_freqs[&id]["beta"], 2);
+        assert!(index.documents[&id].content.ends_with("epsilon beta"));
+        assert!(index.search_query("delta").is_empty());
+    }
+
+    #[test]
+    fn language_hint_selects_ngram_tokenization() {
+        let mut index = Index::builder()
+            .language("ja", TokenStrategy::NGrams(2))
+            .build();
+
+        let english = Document {
+            lang: Some("en".to_string()),
+            ..test_doc("en.md", "Tokyo trip notes")
+        };
+        let japanese = Document {
+            id: DocumentId::random(),
+            path: PathBuf::from("ja.md"),
+            content: "東京旅行のメモ".to_string(),
+            lang: Some("JA".to_string()),
+            ..english.clone()
+        };
+        let (english_id, japanese_id) = (english.id, japanese.id);
+
+        index.add_document(english);
+        index.add_document(japanese);
+
+        // A word inside the unspaced Japanese text is found through n-grams
+        assert_eq!(index.search_query("旅行"), vec![japanese_id]);
+        assert_eq!(index.search_query("東京"), vec![japanese_id]);
+
+        // English queries fall back to words and don't touch the CJK doc
+        assert_eq!(index.search_query("tokyo"), vec![english_id]);
+        assert!(!index.postings.contains_key("to"));
+    }
+
+    #[test]
+    fn search_summary_counts_any_and_all_matches() {
+        let mut index = Index::new();
+
+        let both_a = index.add_text("rust async runtime");
+        let both_b = index.add_text("async rust in practice");
+        index.add_text("rust ownership only");
+        index.add_text("async javascript only");
+        index.add_text("unrelated gardening");
+
+        let summary = index.search_summary("rust async");
+
+        assert_eq!(summary.any_count, 4);
+        assert_eq!(summary.all_count, 2);
+        assert_eq!(summary.all_ids.len(), 2);
+        assert!(summary.all_ids.contains(&both_a));
+        assert!(summary.all_ids.contains(&both_b));
+
+        // Consistent with the plain OR and AND searches
+        assert_eq!(summary.any_count, index.search_query("rust async").len());
+        assert_eq!(summary.all_ids, index.search_all("rust async"));
+
+        // A term nobody has means no AND matches
+        let none = index.search_summary("rust missing");
+        assert_eq!(none.any_count, 3);
+        assert_eq!(none.all_count, 0);
+        assert_eq!(index.search_summary(""), SearchSummary::default());
+    }
+
+    #[test]
+    fn synonyms_expand_query_tokens() {
+        let mut index = Index::new();
+
+        let doc = test_doc("garage.txt", "The automobile needs new tires");
+        let doc_id = doc.id;
+        index.add_document(doc);
+
+        assert!(index.search_query("car").is_empty());
+
+        index.set_synonyms(HashMap::from([(
+            "Car".to_string(),
+            vec!["automobile".to_string()],
+        )]));
+
+        assert_eq!(index.search_query("car"), vec![doc_id]);
+        assert_eq!(index.search_ranked("car")[0].0, doc_id);
+
+        // Expansion is query-side only and one-directional
+        assert!(!index.postings.contains_key("car"));
+        assert_eq!(index.search_query("automobile"), vec![doc_id]);
+    }
+
+    #[test]
+    fn add_text_indexes_content_without_a_file() {
+        let mut index = Index::new();
+
+        let first = index.add_text("piped from stdin");
+        let second = index.add_text("another stdin snippet");
+
+        assert_eq!(index.search_query("piped"), vec![first]);
+        assert_eq!(index.search_query("stdin").len(), 2);
+
+        // Synthetic paths keep both documents addressable
+        assert_eq!(index.path_to_ids.len(), 2);
+        assert_eq!(index.documents[&second].modified, None);
+        assert_eq!(
+            index.documents[&second].path,
+            PathBuf::from(format!("text:{}", second))
+        );
+    }
+
+    #[test]
+    fn document_source_survives_save_and_load() {
+        let mut index = Index::new();
+        let piped = index.add_text_from("rust from stdin", DocumentSource::Stdin);
+        let memory = index.add_text("rust in memory");
+        let file_id = DocumentId::random();
+        index.add_document(Document {
+            id: file_id,
+            ..test_doc("notes/rust.md", "rust on disk")
+        });
+
+        let path = std::env::temp_dir().join(format!("source_{}.json", Uuid::new_v4()));
+        index.save_to_disk(&path).unwrap();
+        let loaded = Index::load_from_disk(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.documents[&piped].source, DocumentSource::Stdin);
+        assert_eq!(loaded.documents[&memory].source, DocumentSource::Memory);
+        assert_eq!(
+            loaded.search_by_source("rust", DocumentSource::File),
+            vec![file_id]
+        );
+        assert_eq!(
+            loaded.search_by_source("rust", DocumentSource::Stdin),
+            vec![piped]
+        );
+        assert!(
+            loaded
+                .search_by_source("rust", DocumentSource::Imported)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn re_ingesting_with_deterministic_ids_replaces_old_terms() {
+        use crate::ingestion::{LoadOptions, load_documents_with};
+
+        let dir = std::env::temp_dir().join(format!("reingest_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("note.md");
+        let options = LoadOptions {
+            deterministic_ids: true,
+            ..LoadOptions::default()
+        };
+
+        fs::write(&file, "alpha beta").unwrap();
+        let mut index = Index::new();
+        index.add_documents(load_documents_with(&dir, &options).unwrap());
+
+        fs::write(&file, "gamma").unwrap();
+        index.add_documents(load_documents_with(&dir, &options).unwrap());
+
+        assert_eq!(index.document_count(), 1);
+        assert!(index.search_query("alpha").is_empty());
+        assert_eq!(index.search_query("gamma").len(), 1);
+        assert_eq!(index.verify(), Ok(()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_by_source_skips_postings_to_missing_documents() {
+        let mut index = Index::new();
+        let kept = index.add_text_from("rust kept", DocumentSource::Stdin);
+        let dangling = index.add_text_from("rust dangling", DocumentSource::Stdin);
+
+        // An inconsistent index (see `verify`) mustn't make this panic
+        index.documents.remove(&dangling);
 
-        assert_eq!(index.postings["hello"].len(), 1);
-        assert_eq!(index.postings["world"].len(), 1);
+        assert_eq!(
+            index.search_by_source("rust", DocumentSource::Stdin),
+            vec![kept]
+        );
     }
 
     #[test]
-    fn add_two_docs() {
+    fn posting_iter_yields_the_posting_set() {
         let mut index = Index::new();
+        index.add_text("rust notes");
+        index.add_text("more rust");
+        index.add_text("gardening");
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Hello world".to_string(),
-            modified: None,
-        };
+        let streamed: HashSet<DocumentId> = index.posting_iter("Rust").collect();
+        let expected: HashSet<DocumentId> = index.postings["rust"].iter().copied().collect();
+        assert_eq!(streamed.len(), 2);
+        assert_eq!(streamed, expected);
 
-        let doc2 = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Hello world how are you friend?".to_string(),
-            modified: None,
-        };
+        assert_eq!(index.posting_iter("missing").count(), 0);
+        assert_eq!(index.posting_iter("!!!").count(), 0);
+    }
 
-        // Grab ids before moving doc ownership
-        let doc_id = doc.id;
-        let doc_id_2 = doc2.id;
+    #[test]
+    fn search_within_keeps_only_candidates() {
+        let mut index = Index::new();
+        let first = index.add_text("rust notes");
+        let second = index.add_text("more rust");
 
-        index.add_document(doc);
-        index.add_document(doc2);
+        assert_eq!(index.search_query("rust").len(), 2);
 
-        assert!(index.postings["hello"].contains(&doc_id));
-        assert!(index.postings["friend"].contains(&doc_id_2));
+        let candidates = HashSet::from([second]);
+        assert_eq!(index.search_within("rust", &candidates), vec![second]);
 
-        assert_eq!(index.postings["hello"].len(), 2);
-        assert_eq!(index.postings["world"].len(), 2);
+        // Candidates that don't match the query don't sneak in
+        let candidates = HashSet::from([first, DocumentId::random()]);
+        assert_eq!(
+            index.search_within("more", &candidates),
+            Vec::<DocumentId>::new()
+        );
+        assert!(index.search_within("rust", &HashSet::new()).is_empty());
     }
 
     #[test]
@@ -201,8 +1781,8 @@ mod tests {
         let index = Index::new();
         let query = "";
 
-        let search_results = index.search_query(&query);
-        let empty_vec: Vec<Uuid> = Vec::new();
+        let search_results = index.search_query(query);
+        let empty_vec: Vec<DocumentId> = Vec::new();
 
         assert_eq!(search_results, empty_vec);
     }
@@ -212,12 +1792,10 @@ mod tests {
         let query = "I believe";
         let mut index = Index::new();
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "I believe that we will win because we are champtions at hear".to_string(),
-            modified: None,
-        };
+        let doc = test_doc(
+            "note.txt",
+            "I believe that we will win because we are champtions at hear",
+        );
 
         let doc_id = doc.id;
         index.add_document(doc);
@@ -233,19 +1811,9 @@ mod tests {
         let query = "believe victory";
         let mut index = Index::new();
 
-        let doc1 = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note1.txt"),
-            content: "I believe in hard work".to_string(),
-            modified: None,
-        };
+        let doc1 = test_doc("note1.txt", "I believe in hard work");
 
-        let doc2 = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note2.txt"),
-            content: "Victory comes to the prepared".to_string(),
-            modified: None,
-        };
+        let doc2 = test_doc("note2.txt", "Victory comes to the prepared");
 
         let doc_id = doc1.id;
         let doc_id_2 = doc2.id;
@@ -255,9 +1823,31 @@ mod tests {
 
         let results = index.search_query(query);
 
-        assert_eq!(results.len(), 2);
-        assert!(results.contains(&doc_id));
-        assert!(results.contains(&doc_id_2));
+        // note1.txt sorts before note2.txt
+        assert_eq!(results, vec![doc_id, doc_id_2]);
+    }
+
+    #[test]
+    fn search_orders_results_by_path() {
+        let mut index = Index::new();
+
+        // Add in reverse path order so insertion order can't fake the result
+        let paths = ["c.txt", "b.txt", "a.txt"];
+        let mut ids = Vec::new();
+
+        for path in paths {
+            let doc = test_doc(path, "shared words");
+
+            ids.push(doc.id);
+            index.add_document(doc);
+        }
+
+        let results = index.search_query("shared");
+
+        assert_eq!(results, vec![ids[2], ids[1], ids[0]]);
+
+        // Same query again must give the exact same sequence
+        assert_eq!(index.search_query("shared"), results);
     }
 
     #[test]
@@ -265,12 +1855,10 @@ mod tests {
         let query = "I will";
         let mut index = Index::new();
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Sometimes you just want a chicken sandwich, lol".to_string(),
-            modified: None,
-        };
+        let doc = test_doc(
+            "note.txt",
+            "Sometimes you just want a chicken sandwich, lol",
+        );
 
         index.add_document(doc);
 
@@ -286,12 +1874,10 @@ mod tests {
         let query = "Good morning";
         let mut index = Index::new();
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "I just want to say good morning, friends! So, good morning!".to_string(),
-            modified: None,
-        };
+        let doc = test_doc(
+            "note.txt",
+            "I just want to say good morning, friends! So, good morning!",
+        );
 
         let doc_id = doc.id;
 
@@ -308,19 +1894,9 @@ mod tests {
     fn remove_document_removes_doc_from_postings() {
         let mut index = Index::new();
 
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "I believe that we will win".to_string(),
-            modified: None,
-        };
+        let doc = test_doc("note.txt", "I believe that we will win");
 
-        let doc2 = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "I believe!".to_string(),
-            modified: None,
-        };
+        let doc2 = test_doc("note.txt", "I believe!");
 
         let doc1_id = doc.id;
         let doc2_id = doc2.id;
@@ -342,8 +1918,8 @@ mod tests {
         assert!(!index.postings.contains_key("win"));
 
         // Check documents map
-        assert!(index.documents.get(&doc1_id).is_none());
-        assert!(index.documents.get(&doc2_id).is_some());
+        assert!(!index.documents.contains_key(&doc1_id));
+        assert!(index.documents.contains_key(&doc2_id));
     }
 
     #[test]
@@ -352,12 +1928,7 @@ mod tests {
         let mut index = Index::new();
 
         // 2. Create a document with unique tokens
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Some unique tokens here".to_string(),
-            modified: None,
-        };
+        let doc = test_doc("note.txt", "Some unique tokens here");
         let doc_id = doc.id;
 
         // 3. Add the document to the index
@@ -386,8 +1957,8 @@ mod tests {
         }
 
         // 7. Assert the document itself is gone
-        assert!(index.documents.get(&doc_id).is_none());
-        assert!(index.doc_tokens.get(&doc_id).is_none());
+        assert!(!index.documents.contains_key(&doc_id));
+        assert!(!index.doc_tokens.contains_key(&doc_id));
     }
 
     #[test]
@@ -396,12 +1967,7 @@ mod tests {
         let mut index = Index::new();
 
         // 2. Create a document with a path
-        let doc = Document {
-            id: Uuid::new_v4(),
-            path: PathBuf::from("note.txt"),
-            content: "Some unique tokens here".to_string(),
-            modified: None,
-        };
+        let doc = test_doc("note.txt", "Some unique tokens here");
 
         // 3. Capture doc_id and path before moving the document
         let doc_id = doc.id;
@@ -412,11 +1978,11 @@ mod tests {
         // 5. Assert document exists in:
         //    - documents
         //    - doc_tokens
-        //    - path_to_id
+        //    - path_to_ids
         let path_buf = PathBuf::from("note.txt");
         assert!(index.documents.contains_key(&doc_id));
         assert!(index.doc_tokens.contains_key(&doc_id));
-        assert_eq!(index.path_to_id.get(&path_buf), Some(&doc_id));
+        assert_eq!(index.id_for_path(&path_buf), Some(doc_id));
 
         // 6. Remove the document
         index.remove_document(doc_id);
@@ -424,10 +1990,448 @@ mod tests {
         // 7. Assert document no longer exists in:
         //    - documents
         //    - doc_tokens
-        //    - path_to_id
+        //    - path_to_ids
         assert!(!index.documents.contains_key(&doc_id));
         assert!(!index.doc_tokens.contains_key(&doc_id));
-        assert!(index.path_to_id.get(&path_buf).is_none());
+        assert!(!index.path_to_ids.contains_key(&path_buf));
+    }
+
+    // Three docs that all match "shared" once, with paths and modified times
+    // in different orders
+    fn order_by_corpus(order_by: OrderBy) -> (Index, [DocumentId; 3]) {
+        use std::time::{Duration, SystemTime};
+
+        let mut index = Index::builder().order_by(order_by).build();
+        let now = SystemTime::now();
+
+        let specs = [
+            ("a.txt", Some(now - Duration::from_secs(300)), "shared"),
+            ("b.txt", None, "shared extra"),
+            ("c.txt", Some(now), "shared"),
+        ];
+
+        let mut ids = [DocumentId::from(Uuid::nil()); 3];
+        for (i, (path, modified, content)) in specs.into_iter().enumerate() {
+            let doc = test_doc_modified(path, content, modified);
+            ids[i] = doc.id;
+            index.add_document(doc);
+        }
+
+        (index, ids)
+    }
+
+    #[test]
+    fn order_by_path_asc_is_the_default() {
+        let (index, [a, b, c]) = order_by_corpus(OrderBy::default());
+
+        assert_eq!(index.search_query("shared"), vec![a, b, c]);
+
+        let ranked: Vec<DocumentId> = index.search_ranked("shared").iter().map(|r| r.0).collect();
+        // b is longer so scores lower; a and c tie and fall back to path
+        assert_eq!(ranked, vec![a, c, b]);
+    }
+
+    #[test]
+    fn order_by_modified_desc_puts_newest_first() {
+        let (index, [a, b, c]) = order_by_corpus(OrderBy::ModifiedDesc);
+
+        // c is newest, a older, b has no timestamp so goes last
+        assert_eq!(index.search_query("shared"), vec![c, a, b]);
+
+        let ranked: Vec<DocumentId> = index.search_ranked("shared").iter().map(|r| r.0).collect();
+        assert_eq!(ranked, vec![c, a, b]);
+    }
+
+    #[test]
+    fn order_by_score_then_path_ranks_unranked_union() {
+        let (index, [a, b, c]) = order_by_corpus(OrderBy::ScoreThenPath);
+
+        // b matches both terms, a and c one each and tie on path
+        assert_eq!(index.search_query("shared extra"), vec![b, a, c]);
+        assert_eq!(index.search_query("shared"), vec![a, b, c]);
+    }
+
+    #[test]
+    fn search_dedup_by_path_collapses_same_path_hits() {
+        let mut index = Index::new();
+
+        let weak = test_doc("note.txt", "rust basics");
+
+        let strong = test_doc("note.txt", "rust async deep dive");
+
+        let other = test_doc("other.txt", "async only");
+
+        let strong_id = strong.id;
+        let other_id = other.id;
+
+        index.add_document(weak);
+        index.add_document(strong);
+        index.add_document(other);
+
+        // Plain search still sees both note.txt documents
+        assert_eq!(index.search_query("rust async").len(), 3);
+
+        // Dedup keeps the note.txt doc matching both terms
+        let results = index.search_dedup_by_path("rust async");
+        assert_eq!(results, vec![strong_id, other_id]);
+    }
+
+    #[test]
+    fn positions_are_capped_per_term() {
+        let mut index = Index::builder().max_positions_per_term(3).build();
+        let id = index.add_text("no no no no no no yes no");
+
+        assert_eq!(index.positions[&id]["no"], vec![0, 1, 2]);
+        assert_eq!(index.positions[&id]["yes"], vec![6]);
+
+        // Frequencies still count every occurrence
+        assert_eq!(index.term_freqs[&id]["no"], 7);
+
+        // Proximity beyond the cap degrades to a miss, not an error
+        assert!(index.search_near("no", "yes", 1).is_empty());
+        assert_eq!(index.search_near("no", "no", 1), vec![id]);
+    }
+
+    #[test]
+    fn reload_if_changed_only_reloads_newer_files() {
+        let path = std::env::temp_dir().join(format!("reload_{}.json", Uuid::new_v4()));
+
+        let mut writer = Index::new();
+        writer.add_text("first version");
+        writer.save_to_disk(&path).unwrap();
+
+        let mut reader = Index::load_from_disk(&path).unwrap();
+        let version = Index::disk_version(&path).unwrap();
+
+        // Untouched file: nothing to do
+        assert_eq!(reader.reload_if_changed(&path, version).unwrap(), None);
+
+        // Another process saves (with a clearly later mtime)
+        writer.add_text("second version");
+        writer.save_to_disk(&path).unwrap();
+        let later = version + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        assert_eq!(
+            reader.reload_if_changed(&path, version).unwrap(),
+            Some(later)
+        );
+        assert_eq!(reader.search_query("second").len(), 1);
+        assert_eq!(reader.reload_if_changed(&path, later).unwrap(), None);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn document_paths_lists_ingested_files() {
+        let mut index = Index::new();
+        let file = |path: &str| test_doc(path, "some words");
+
+        index.add_documents([file("notes/b.md"), file("a.txt"), file("notes/c.md")]);
+        index.add_text("not a file");
+        index.add_text_from("piped", DocumentSource::Stdin);
+
+        assert_eq!(
+            index.document_paths(),
+            vec![
+                &PathBuf::from("a.txt"),
+                &PathBuf::from("notes/b.md"),
+                &PathBuf::from("notes/c.md"),
+            ]
+        );
+    }
+
+    #[test]
+    fn upsert_path_reads_and_indexes_the_file() {
+        let dir = std::env::temp_dir().join(format!("upsert_path_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        fs::write(&path, "first draft about lifetimes").unwrap();
+
+        let mut index = Index::new();
+        let id = index.upsert_path(&path).unwrap();
+        assert_eq!(index.search_query("lifetimes"), vec![id]);
+
+        // Re-upserting after an edit keeps the id and drops the old terms
+        fs::write(&path, "final version about borrowing").unwrap();
+        assert_eq!(index.upsert_path(&path).unwrap(), id);
+        assert_eq!(index.search_query("borrowing"), vec![id]);
+        assert!(index.search_query("lifetimes").is_empty());
+        assert_eq!(index.document_count(), 1);
+
+        assert!(matches!(
+            index.upsert_path(&dir.join("missing.md")),
+            Err(IngestError::NotFile)
+        ));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn search_grouped_buckets_by_parent_directory() {
+        let mut index = Index::new();
+        let a = test_doc("notes/rust/a.md", "rust traits");
+        let b = test_doc("notes/rust/b.md", "rust macros");
+        let c = test_doc("notes/go/c.md", "rust vs go");
+        let unrelated = test_doc("notes/go/d.md", "goroutines");
+        let root = test_doc("/", "rust at the root");
+        let (a_id, b_id, c_id, root_id) = (a.id, b.id, c.id, root.id);
+        index.add_documents([a, b, c, unrelated, root]);
+
+        let groups = index.search_grouped("rust");
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[Path::new("notes/rust")], vec![a_id, b_id]);
+        assert_eq!(groups[Path::new("notes/go")], vec![c_id]);
+        assert_eq!(groups[Path::new("")], vec![root_id]);
+        assert!(index.search_grouped("missing").is_empty());
+    }
+
+    #[test]
+    fn documents_sharing_a_path_are_all_tracked() {
+        let mut index = Index::new();
+        let note = |content: &str| test_doc("note.txt", content);
+        let path = Path::new("note.txt");
+
+        let (first, second, third) = (note("first"), note("second"), note("third"));
+        let (first_id, second_id) = (first.id, second.id);
+        index.add_document(first);
+        index.add_document(second);
+
+        let mut both = vec![first_id, second_id];
+        both.sort();
+        assert_eq!(index.ids_for_path(path), both);
+        assert_eq!(index.id_for_path(path), Some(both[0]));
+        assert!(index.verify().is_ok());
+
+        // Removing one by id leaves the other reachable by path
+        index.remove_document(first_id);
+        assert_eq!(index.ids_for_path(path), vec![second_id]);
+        assert!(index.verify().is_ok());
+
+        // Upsert replaces everything at the path
+        index.add_document(note("again"));
+        let third_id = third.id;
+        index.upsert_document(third);
+        assert_eq!(index.ids_for_path(path), vec![third_id]);
+        assert_eq!(index.document_count(), 1);
+
+        // Removing by path takes every document there
+        index.add_document(note("fourth"));
+        assert_eq!(index.remove_documents_by_paths(&[path.to_path_buf()]), 2);
+        assert!(index.is_empty());
+        assert!(!index.remove_document_by_path(path));
+        assert!(index.verify().is_ok());
+    }
+
+    #[test]
+    fn loads_single_id_path_maps_from_older_files() {
+        let mut index = Index::new();
+        let id = index.add_text("legacy");
+        let path = index.document(id).unwrap().path.clone();
+
+        // Older files had `"path_to_id": {"<path>": "<id>"}`
+        let mut json = serde_json::to_value(&index).unwrap();
+        let map = json.as_object_mut().unwrap();
+        map.remove("path_to_ids");
+        map.insert(
+            "path_to_id".to_string(),
+            serde_json::json!({ path.to_string_lossy(): id }),
+        );
+
+        let loaded: Index = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.ids_for_path(&path), vec![id]);
+        assert_eq!(loaded, index);
+    }
+
+    #[test]
+    fn search_dedup_by_path_groups_chunks_by_parent() {
+        use crate::ingestion::{ChunkStrategy, chunk_document};
+
+        let mut index = Index::new();
+
+        let doc = test_doc("long.md", "rust intro\n\nmore rust\n\nrust outro");
+
+        let chunks = chunk_document(&doc, ChunkStrategy::Paragraph);
+        assert_eq!(chunks.len(), 3);
+
+        for chunk in chunks {
+            index.add_document(chunk);
+        }
+
+        assert_eq!(index.search_query("rust").len(), 3);
+        assert_eq!(index.search_dedup_by_path("rust").len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_to_disk_returns_error_for_unserializable_index() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut index = Index::new();
+
+        // JSON map keys must be strings, a non UTF-8 path can't be one
+        index.add_document(test_doc(OsStr::from_bytes(b"bad\xff.txt"), "hello"));
+
+        let out = std::env::temp_dir().join(format!("index_{}.json", Uuid::new_v4()));
+        let err = index.save_to_disk(&out).unwrap_err();
+
+        assert!(matches!(err, IndexError::Serde(_)));
+        assert!(!out.exists());
+    }
+
+    #[test]
+    fn load_from_disk_reports_bad_json_and_missing_files() {
+        let dir = std::env::temp_dir();
+        let garbage = dir.join(format!("index_{}.json", Uuid::new_v4()));
+        fs::write(&garbage, "{ not json").unwrap();
+
+        assert!(matches!(
+            Index::load_from_disk(&garbage),
+            Err(IndexError::Serde(_))
+        ));
+
+        let missing = dir.join(format!("missing_{}.json", Uuid::new_v4()));
+        assert!(matches!(
+            Index::load_from_disk(&missing),
+            Err(IndexError::Io(_))
+        ));
+
+        fs::remove_file(garbage).unwrap();
+    }
+
+    #[test]
+    fn load_from_disk_rejects_other_index_versions() {
+        let mut index = Index::new();
+        index.add_text("versioned");
+        let path = std::env::temp_dir().join(format!("index_{}.json", Uuid::new_v4()));
+
+        // A future format, with fields this version doesn't understand
+        let mut json = serde_json::to_value(&index).unwrap();
+        json["version"] = serde_json::json!(INDEX_VERSION + 1);
+        json["postings"] = serde_json::json!("some new layout");
+        fs::write(&path, json.to_string()).unwrap();
+
+        let err = Index::load_from_disk(&path).unwrap_err();
+        assert!(matches!(
+            err,
+            IndexError::VersionMismatch { found, expected: INDEX_VERSION }
+                if found == INDEX_VERSION + 1
+        ));
+        assert!(err.to_string().contains("please reindex"));
+
+        // Files from before the version was recorded still load
+        json.as_object_mut().unwrap().remove("version");
+        json["postings"] = serde_json::to_value(&index.postings).unwrap();
+        fs::write(&path, json.to_string()).unwrap();
+        assert_eq!(Index::load_from_disk(&path).unwrap(), index);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    // Hands out at most `step` bytes per read to force awkward seams
+    struct TrickleReader<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..n].copy_from_slice(&self.bytes[..n]);
+            self.bytes = &self.bytes[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn streaming_matches_add_document_across_seams() {
+        let content = "Hello wonderful wörld, naïve tokens-split across seams! café café";
+
+        let mut streamed = Index::new();
+        let mut regular = Index::new();
+
+        // Every step size puts a seam inside some word or multi-byte char
+        for step in 1..8 {
+            let reader = TrickleReader {
+                bytes: content.as_bytes(),
+                step,
+            };
+            let doc_id = streamed
+                .add_document_streaming(PathBuf::from("stream.txt"), reader)
+                .unwrap();
+
+            let doc = test_doc("regular.txt", content);
+            let regular_id = doc.id;
+            regular.add_document(doc);
+
+            assert_eq!(
+                streamed.term_freqs[&doc_id],
+                regular.term_freqs[&regular_id]
+            );
+            assert_eq!(
+                streamed.doc_lengths[&doc_id],
+                regular.doc_lengths[&regular_id]
+            );
+            assert!(streamed.documents[&doc_id].content.is_empty());
+        }
+    }
+
+    #[test]
+    fn streaming_large_file_indexes_token_on_buffer_boundary() {
+        // Put a unique word right across the first buffer boundary
+        let mut content = "filler ".repeat(STREAM_BUFFER_SIZE / 7);
+        content.truncate(STREAM_BUFFER_SIZE - 4);
+        content.push_str(" boundaryword ");
+        content.push_str(&"tail ".repeat(50_000));
+
+        let path = std::env::temp_dir().join(format!("big_{}.txt", Uuid::new_v4()));
+        fs::write(&path, &content).unwrap();
+
+        let mut index = Index::new();
+        let file = fs::File::open(&path).unwrap();
+        let doc_id = index.add_document_streaming(path.clone(), file).unwrap();
+
+        assert_eq!(index.search_query("boundaryword"), vec![doc_id]);
+        assert_eq!(index.term_freqs[&doc_id]["tail"], 50_000);
+        assert!(!index.postings.contains_key("bound"));
+        assert!(!index.postings.contains_key("aryword"));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn retain_documents_prunes_failing_documents() {
+        let mut index = Index::new();
+
+        let mut ids = Vec::new();
+        for (path, content) in [
+            ("keep.md", "markdown shared"),
+            ("drop.txt", "plain shared textonly"),
+            ("also_keep.md", "more markdown"),
+        ] {
+            let doc = test_doc(path, content);
+            ids.push(doc.id);
+            index.add_document(doc);
+        }
+
+        index.retain_documents(|doc| doc.path.extension().is_some_and(|ext| ext == "md"));
+
+        assert_eq!(index.documents.len(), 2);
+        assert!(!index.documents.contains_key(&ids[1]));
+        assert!(!index.doc_tokens.contains_key(&ids[1]));
+        assert!(!index.path_to_ids.contains_key(&PathBuf::from("drop.txt")));
+
+        // Tokens only the removed doc had are gone, shared ones lose the id
+        assert!(!index.postings.contains_key("textonly"));
+        assert!(!index.postings["shared"].contains(&ids[1]));
+        assert_eq!(index.search_query("markdown").len(), 2);
     }
 
     #[test]
@@ -435,19 +2439,9 @@ mod tests {
         let mut index = Index::new();
         let path = PathBuf::from("note.txt");
 
-        let doc1 = Document {
-            id: Uuid::new_v4(),
-            path: path.clone(),
-            content: "hello world".to_string(),
-            modified: None,
-        };
+        let doc1 = test_doc(&path, "hello world");
 
-        let doc2 = Document {
-            id: Uuid::new_v4(),
-            path: path.clone(),
-            content: "goodbye world".to_string(),
-            modified: None,
-        };
+        let doc2 = test_doc(&path, "goodbye world");
 
         index.upsert_document(doc1);
         index.upsert_document(doc2);
@@ -455,4 +2449,211 @@ mod tests {
         let results = index.search_query("goodbye");
         assert_eq!(results.len(), 1);
     }
+
+    #[test]
+    fn upsert_refreshes_indexed_at() {
+        let mut index = Index::new();
+        let id = DocumentId::random();
+        let doc = Document {
+            id,
+            path: PathBuf::from("note.txt"),
+            content: "first draft".to_string(),
+            modified: None,
+            chunk: None,
+            source: DocumentSource::File,
+            lang: None,
+        };
+
+        let before = SystemTime::now();
+        index.upsert_document(doc.clone());
+        let first = index.indexed_at(id).unwrap();
+        assert!(first >= before);
+
+        std::thread::sleep(Duration::from_millis(10));
+        index.upsert_document(Document {
+            content: "second draft".to_string(),
+            ..doc
+        });
+        assert!(index.indexed_at(id).unwrap() > first);
+
+        index.remove_document(id);
+        assert_eq!(index.indexed_at(id), None);
+    }
+
+    #[test]
+    fn token_histogram_counts_every_occurrence() {
+        let mut index = Index::new();
+        index.add_text("rust rust tokio");
+        index.add_text("rust serde");
+        index.add_text("serde tokio");
+
+        assert_eq!(
+            index.token_histogram(),
+            vec![
+                ("rust".to_string(), 3),
+                ("serde".to_string(), 2),
+                ("tokio".to_string(), 2),
+            ]
+        );
+        assert!(Index::new().token_histogram().is_empty());
+    }
+
+    #[test]
+    fn different_spellings_of_a_path_are_one_document() {
+        let mut index = Index::new();
+
+        index.upsert_document(test_doc("./notes/a.txt", "first draft"));
+        index.upsert_document(test_doc("notes/drafts/../a.txt", "second draft"));
+
+        assert_eq!(index.document_count(), 1);
+        assert_eq!(index.document_paths(), vec![Path::new("notes/a.txt")]);
+        assert!(index.search_query("first").is_empty());
+        assert_eq!(index.ids_for_path(Path::new("notes//a.txt")).len(), 1);
+
+        assert!(index.remove_document_by_path(Path::new("./notes/a.txt")));
+        assert!(index.is_empty());
+        assert!(index.path_to_ids.is_empty());
+    }
+
+    #[test]
+    fn search_for_each_visits_every_match_once() {
+        let mut index = Index::new();
+        index.add_text("rust and tokio");
+        index.add_text("rust only");
+        index.add_text("tokio only");
+        index.add_text("nothing relevant");
+
+        for query in ["rust tokio", "rust", "missing", ""] {
+            let mut visited = Vec::new();
+            index.search_for_each(query, |id| visited.push(id));
+
+            let mut expected = index.search_query(query);
+            visited.sort();
+            expected.sort();
+            assert_eq!(visited, expected, "{query:?}");
+        }
+    }
+
+    #[test]
+    fn rename_document_moves_the_path_without_reindexing() {
+        let mut index = Index::new();
+
+        let moved = test_doc("notes/old.md", "rust lifetimes");
+        let replaced = test_doc("notes/new.md", "stale content");
+        let moved_id = moved.id;
+        index.add_documents([moved, replaced]);
+
+        assert!(index.rename_document(Path::new("notes/old.md"), Path::new("./notes/new.md")));
+
+        assert_eq!(index.search_query("lifetimes"), vec![moved_id]);
+        assert!(index.search_query("stale").is_empty());
+        assert_eq!(index.id_for_path(Path::new("notes/new.md")), Some(moved_id));
+        assert_eq!(index.id_for_path(Path::new("notes/old.md")), None);
+        assert_eq!(
+            index.document(moved_id).unwrap().path,
+            Path::new("notes/new.md")
+        );
+        assert_eq!(index.verify(), Ok(()));
+
+        assert!(!index.rename_document(Path::new("notes/old.md"), Path::new("elsewhere.md")));
+    }
+
+    #[test]
+    fn terms_for_document_matches_the_tokenized_content() {
+        let mut index = Index::new();
+        let content = "Rust traits, rust generics & more traits";
+        let id = index.add_text(content);
+
+        let expected: HashSet<String> = crate::tokenizer::tokenize(content).into_iter().collect();
+        assert_eq!(index.terms_for_document(id), Some(&expected));
+        assert_eq!(expected.len(), 4);
+
+        assert_eq!(index.terms_for_document(DocumentId::random()), None);
+    }
+
+    #[test]
+    fn search_excluding_drops_documents_with_excluded_terms() {
+        let mut index = Index::new();
+        let kept = index.add_text("rust web servers");
+        let dropped = index.add_text("rust web frameworks in javascript");
+        index.add_text("javascript only");
+
+        assert_eq!(index.search_query("rust web").len(), 2);
+        assert_eq!(index.search_excluding("rust web", "JavaScript"), vec![kept]);
+
+        // Any excluded token is enough, unknown ones exclude nothing
+        assert!(
+            index
+                .search_excluding("rust", "servers frameworks")
+                .is_empty()
+        );
+        let all = index.search_query("rust");
+        assert!(all.contains(&dropped));
+        assert_eq!(index.search_excluding("rust", "missing"), all);
+        assert_eq!(index.search_excluding("rust", ""), all);
+    }
+
+    #[test]
+    fn from_documents_matches_adding_one_by_one() {
+        let docs: Vec<Document> = ["rust traits", "rust macros", "go channels"]
+            .iter()
+            .enumerate()
+            .map(|(i, content)| test_doc(format!("note{i}.md"), content))
+            .collect();
+
+        let built = Index::from_documents(docs.clone());
+        let mut manual = Index::new();
+        for doc in docs {
+            manual.add_document(doc);
+        }
+
+        // indexed_at is a wall-clock timestamp, the only thing allowed to differ
+        manual.indexed_at = built.indexed_at.clone();
+        assert_eq!(built, manual);
+        assert_eq!(built.search_query("rust").len(), 2);
+    }
+
+    #[test]
+    fn compact_save_loads_back_identically_and_is_smaller() {
+        let mut index = Index::new();
+        for i in 0..20 {
+            index.add_text(&format!("rust note number{i} with a few more words"));
+        }
+
+        let dir = std::env::temp_dir();
+        let pretty_path = dir.join(format!("pretty_{}.json", Uuid::new_v4()));
+        let compact_path = dir.join(format!("compact_{}.json", Uuid::new_v4()));
+        index.save_to_disk(&pretty_path).unwrap();
+        index.save_to_disk_compact(&compact_path).unwrap();
+
+        let pretty_len = fs::metadata(&pretty_path).unwrap().len();
+        let compact_len = fs::metadata(&compact_path).unwrap().len();
+        let loaded = Index::load_from_disk(&compact_path).unwrap();
+        fs::remove_file(&pretty_path).unwrap();
+        fs::remove_file(&compact_path).unwrap();
+
+        assert!(compact_len < pretty_len, "{compact_len} vs {pretty_len}");
+        assert_eq!(loaded, index);
+    }
+
+    #[test]
+    fn remove_orphan_documents_drops_vanished_files() {
+        let dir = std::env::temp_dir().join(format!("orphans_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("kept.md"), "rust kept").unwrap();
+        fs::write(dir.join("gone.md"), "rust gone").unwrap();
+
+        let mut index = Index::from_documents(crate::ingestion::load_documents(&dir).unwrap());
+        let typed = index.add_text("rust typed in");
+        assert_eq!(index.remove_orphan_documents(), 0);
+
+        fs::remove_file(dir.join("gone.md")).unwrap();
+        assert_eq!(index.remove_orphan_documents(), 1);
+
+        assert!(index.search_query("gone").is_empty());
+        assert_eq!(index.search_query("kept").len(), 1);
+        assert!(index.document(typed).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
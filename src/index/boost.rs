@@ -0,0 +1,75 @@
+//! `term^boost` weights in ranked queries, e.g. `rust^2 async`.
+
+use super::Index;
+use crate::tokenizer::tokenize_with;
+use std::collections::HashMap;
+
+/// A query with its `^boost` suffixes taken out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(super) struct BoostedQuery {
+    /// The query as plain text, ready to tokenize
+    pub text: String,
+    /// Weight per token; tokens not listed weigh 1.0
+    pub boosts: HashMap<String, f32>,
+}
+
+impl BoostedQuery {
+    pub fn boost(&self, token: &str) -> f32 {
+        self.boosts.get(token).copied().unwrap_or(1.0)
+    }
+}
+
+impl Index {
+    // Splits `^<number>` off the end of each query word. The boost applies
+    // to every token of that word (not to its synonyms). Anything that isn't
+    // a finite, non-negative number after the `^` is left in the text as is.
+    pub(super) fn parse_boosts(&self, query: &str) -> BoostedQuery {
+        let mut parsed = BoostedQuery::default();
+
+        for word in query.split_whitespace() {
+            let boosted = word.rsplit_once('^').and_then(|(term, boost)| {
+                let boost: f32 = boost.parse().ok()?;
+                (boost.is_finite() && boost >= 0.0).then_some((term, boost))
+            });
+
+            let term = match boosted {
+                Some((term, boost)) => {
+                    // Repeated terms keep their biggest boost
+                    for token in tokenize_with(term, &self.config.tokenizer) {
+                        let weight = parsed.boosts.entry(token).or_insert(boost);
+                        *weight = weight.max(boost);
+                    }
+                    term
+                }
+                None => word,
+            };
+
+            if !parsed.text.is_empty() {
+                parsed.text.push(' ');
+            }
+            parsed.text.push_str(term);
+        }
+
+        parsed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boosts_are_split_off_query_words() {
+        let index = Index::new();
+        let parsed = index.parse_boosts("Rust^2 async  tokio^0.5 c++^x");
+
+        assert_eq!(parsed.text, "Rust async tokio c++^x");
+        assert_eq!(parsed.boost("rust"), 2.0);
+        assert_eq!(parsed.boost("tokio"), 0.5);
+        assert_eq!(parsed.boost("async"), 1.0);
+        assert_eq!(parsed.boosts.len(), 2);
+
+        // Not a usable number: the word is left alone
+        assert_eq!(index.parse_boosts("a^-1 b^inf").text, "a^-1 b^inf");
+    }
+}
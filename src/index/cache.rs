@@ -0,0 +1,142 @@
+//! Optional LRU cache of `search_query` results.
+//!
+//! Entries are tagged with the index generation they were computed at. Every
+//! mutation bumps the generation, so stale entries simply stop matching and
+//! get replaced; nothing has to walk the cache on writes.
+
+use crate::ingestion::DocumentId;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Not part of the index's data: skipped by serde and always equal, so two
+/// indexes with the same documents compare equal whatever they've cached.
+#[derive(Debug, Default)]
+pub(super) struct QueryCache {
+    generation: u64,
+    // Behind a lock because searches only borrow the index
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    // key -> (generation, results, last use)
+    map: HashMap<String, (u64, Vec<DocumentId>, u64)>,
+    clock: u64,
+}
+
+impl QueryCache {
+    /// Marks every cached result as stale.
+    pub(super) fn invalidate(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Cached results for `key`, if computed since the last mutation.
+    pub(super) fn get(&self, key: &str) -> Option<Vec<DocumentId>> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.clock += 1;
+        let now = entries.clock;
+
+        match entries.map.get_mut(key) {
+            Some((generation, results, last_used)) if *generation == self.generation => {
+                *last_used = now;
+                Some(results.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Stores `results` for `key`, evicting the least recently used entry
+    /// (preferring stale ones) when `capacity` is reached.
+    pub(super) fn insert(&self, key: String, results: Vec<DocumentId>, capacity: usize) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+
+        if capacity == 0 {
+            return;
+        }
+
+        if !entries.map.contains_key(&key) && entries.map.len() >= capacity {
+            let generation = self.generation;
+            let victim = entries
+                .map
+                .iter()
+                .min_by_key(|(_, (entry_generation, _, last_used))| {
+                    (*entry_generation == generation, *last_used)
+                })
+                .map(|(key, _)| key.clone());
+
+            if let Some(victim) = victim {
+                entries.map.remove(&victim);
+            }
+        }
+
+        entries.clock += 1;
+        let now = entries.clock;
+        entries.map.insert(key, (self.generation, results, now));
+    }
+
+    #[cfg(test)]
+    pub(super) fn len(&self) -> usize {
+        self.entries.lock().map_or(0, |entries| entries.map.len())
+    }
+}
+
+impl PartialEq for QueryCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::index::Index;
+
+    #[test]
+    fn cached_results_are_reused_until_the_index_changes() {
+        let mut index = Index::with_query_cache(2);
+        let first = index.add_text("rust notes");
+
+        assert_eq!(index.search_query("rust"), vec![first]);
+        assert_eq!(index.query_cache.len(), 1);
+
+        // Same tokens in another order or case hit the same entry
+        assert_eq!(index.search_query("RUST"), vec![first]);
+        assert_eq!(index.query_cache.len(), 1);
+
+        // A mutation makes the cached entry stale
+        let second = index.add_text("more rust");
+        let mut both = vec![first, second];
+        index.sort_by_path(&mut both);
+        assert_eq!(index.search_query("rust"), both);
+
+        index.remove_document(first);
+        assert_eq!(index.search_query("rust"), vec![second]);
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let mut index = Index::with_query_cache(2);
+        index.add_text("alpha beta gamma");
+
+        index.search_query("alpha");
+        index.search_query("beta");
+        index.search_query("alpha");
+        index.search_query("gamma");
+
+        // beta was the least recently used
+        let entries = index.query_cache.entries.lock().unwrap();
+        let mut keys: Vec<&String> = entries.map.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["alpha", "gamma"]);
+    }
+
+    #[test]
+    fn cache_is_off_by_default() {
+        let mut index = Index::new();
+        index.add_text("rust");
+        index.search_query("rust");
+
+        assert_eq!(index.query_cache.len(), 0);
+    }
+}
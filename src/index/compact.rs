@@ -0,0 +1,193 @@
+//! Sizing the index: forgetting rare terms, handing spare capacity back to
+//! the allocator, and reserving it up front for bulk loads.
+
+use super::Index;
+
+impl Index {
+    /// Drops every term found in fewer than `min_doc_frequency` documents,
+    /// returning how many terms were removed.
+    ///
+    /// Meant for hapax terms and numeric noise that pile up over time and
+    /// bloat the index file. This is lossy: searching for a dropped term
+    /// finds nothing until the documents containing it are re-indexed.
+    /// Document lengths are left alone so ranking of the remaining terms
+    /// doesn't shift.
+    pub fn compact(&mut self, min_doc_frequency: usize) -> usize {
+        self.query_cache.invalidate();
+
+        // 1. Find the rare terms
+        let rare: Vec<String> = self
+            .postings
+            .iter()
+            .filter(|(_, ids)| ids.len() < min_doc_frequency)
+            .map(|(term, _)| term.clone())
+            .collect();
+
+        // 2. Forget them everywhere a document refers to them
+        for term in &rare {
+            if let Some(ids) = self.postings.remove(term) {
+                for id in &ids {
+                    self.dirty.mark(*id);
+                    if let Some(tokens) = self.doc_tokens.get_mut(id) {
+                        tokens.remove(term);
+                    }
+                    if let Some(freqs) = self.term_freqs.get_mut(id) {
+                        freqs.remove(term);
+                    }
+                    if let Some(positions) = self.positions.get_mut(id) {
+                        positions.remove(term);
+                    }
+                }
+            }
+        }
+
+        rare.len()
+    }
+
+    /// Shrinks every internal map (and the collections inside them) to fit
+    /// what they currently hold.
+    ///
+    /// Maps never give memory back on their own, so after a bulk removal
+    /// (say reconcile dropping a big folder) a long-running process keeps
+    /// the old peak. This is an O(n) pass over the whole index and the next
+    /// insertions have to grow everything again, so call it after big
+    /// removals rather than routinely. Search results are unaffected.
+    pub fn shrink_to_fit(&mut self) {
+        for ids in self.postings.values_mut() {
+            ids.shrink_to_fit();
+        }
+
+        self.documents.shrink_to_fit();
+        for doc in self.documents.values_mut() {
+            doc.content.shrink_to_fit();
+        }
+
+        self.path_to_ids.shrink_to_fit();
+        for ids in self.path_to_ids.values_mut() {
+            ids.shrink_to_fit();
+        }
+
+        self.doc_tokens.shrink_to_fit();
+        for tokens in self.doc_tokens.values_mut() {
+            tokens.shrink_to_fit();
+        }
+
+        self.term_freqs.shrink_to_fit();
+        for freqs in self.term_freqs.values_mut() {
+            freqs.shrink_to_fit();
+        }
+
+        self.positions.shrink_to_fit();
+        for positions in self.positions.values_mut() {
+            positions.shrink_to_fit();
+            for offsets in positions.values_mut() {
+                offsets.shrink_to_fit();
+            }
+        }
+
+        self.doc_lengths.shrink_to_fit();
+        self.indexed_at.shrink_to_fit();
+        self.synonyms.shrink_to_fit();
+    }
+
+    /// Makes room for `documents` more documents, so a bulk ingest doesn't
+    /// rehash every map over and over as it grows. Purely a performance hint.
+    ///
+    /// Only the per-document maps can be reserved. The postings are a
+    /// `BTreeMap` (kept sorted for fuzzy and prefix matching), which
+    /// allocates node by node and has no capacity, so there's no term count
+    /// to pass.
+    pub fn reserve(&mut self, documents: usize) {
+        self.documents.reserve(documents);
+        self.path_to_ids.reserve(documents);
+        self.doc_tokens.reserve(documents);
+        self.term_freqs.reserve(documents);
+        self.positions.reserve(documents);
+        self.doc_lengths.reserve(documents);
+        self.indexed_at.reserve(documents);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_drops_terms_below_the_threshold() {
+        let mut index = Index::new();
+        let a = index.add_text("rust ownership 1234");
+        let b = index.add_text("rust borrowing");
+        let c = index.add_text("rust ownership");
+
+        assert_eq!(index.compact(2), 2);
+
+        // Hapax terms are gone, including from the per-document maps
+        assert!(index.search_query("1234").is_empty());
+        assert!(index.search_query("borrowing").is_empty());
+        assert!(!index.doc_tokens[&b].contains("borrowing"));
+
+        // Common ones remain
+        assert_eq!(index.search_query("rust").len(), 3);
+        assert_eq!(index.search_query("ownership").len(), 2);
+        assert!(index.search_query("ownership").contains(&a));
+        assert!(index.search_query("ownership").contains(&c));
+        assert!(index.verify().is_ok());
+
+        // Nothing is below a threshold of 0 or 1
+        assert_eq!(index.compact(1), 0);
+    }
+
+    #[test]
+    fn shrink_to_fit_keeps_results_after_bulk_removal() {
+        let mut index = Index::new();
+        let ids: Vec<_> = (0..1000)
+            .map(|i| index.add_text(&format!("shared note number{i}")))
+            .collect();
+
+        for id in &ids[10..] {
+            index.remove_document(*id);
+        }
+
+        let before = index.search_query("shared number3");
+        let capacity = index.documents.capacity();
+
+        index.shrink_to_fit();
+
+        assert!(index.documents.capacity() < capacity);
+        assert_eq!(index.search_query("shared number3"), before);
+        assert_eq!(index.search_ranked("shared").len(), 10);
+        assert_eq!(index.verify(), Ok(()));
+
+        // Still usable afterwards
+        let added = index.add_text("shared again");
+        assert!(index.search_query("again").contains(&added));
+
+        // And an empty index is fine too
+        Index::new().shrink_to_fit();
+    }
+
+    #[test]
+    fn reserve_grows_capacity_without_changing_results() {
+        let mut reserved = Index::new();
+        let mut plain = Index::new();
+
+        reserved.reserve(500);
+        assert!(reserved.documents.capacity() >= 500);
+        assert!(reserved.doc_tokens.capacity() >= 500);
+        assert!(reserved.path_to_ids.capacity() >= 500);
+
+        for i in 0..500 {
+            let text = format!("shared note number{i}");
+            reserved.add_text(&text);
+            plain.add_text(&text);
+        }
+
+        assert_eq!(reserved.document_count(), 500);
+        assert_eq!(reserved.search_query("number42").len(), 1);
+        assert_eq!(
+            reserved.search_ranked("shared").len(),
+            plain.search_ranked("shared").len()
+        );
+        assert_eq!(reserved.verify(), Ok(()));
+    }
+}
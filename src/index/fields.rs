@@ -0,0 +1,210 @@
+//! `field:term` queries over a document's title and body.
+//!
+//! There's no separate per-field index: the body is the regular postings and
+//! the title is the file name (without extension), tokenized when a query
+//! asks for it. Good enough for notes, where the file name is the title.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize_with;
+use std::collections::{HashMap, HashSet};
+
+// How much a hit counts for, per field
+const TITLE_BOOST: f32 = 2.0;
+const BODY_BOOST: f32 = 1.0;
+
+/// A part of a document a query term can be scoped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    /// The file name without its extension.
+    Title,
+    /// The document content.
+    Body,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "title" => Some(Field::Title),
+            "body" => Some(Field::Body),
+            _ => None,
+        }
+    }
+
+    /// Score a hit in this field contributes.
+    pub fn boost(self) -> f32 {
+        match self {
+            Field::Title => TITLE_BOOST,
+            Field::Body => BODY_BOOST,
+        }
+    }
+}
+
+/// One term of a fielded query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldTerm {
+    /// `None` for a bare term, which searches every field.
+    pub field: Option<Field>,
+    /// Normalized like indexed text.
+    pub term: String,
+}
+
+impl Index {
+    /// Splits a query like `title:rust body:async notes` into terms.
+    ///
+    /// A known field name before a `:` scopes the term to that field; bare
+    /// terms (and unknown prefixes, kept as plain text) search all fields.
+    pub fn parse_field_query(&self, query: &str) -> Vec<FieldTerm> {
+        let mut terms = Vec::new();
+
+        for word in query.split_whitespace() {
+            let (field, text) = match word.split_once(':') {
+                Some((name, text)) => match Field::parse(name) {
+                    Some(field) => (Some(field), text),
+                    None => (None, word),
+                },
+                None => (None, word),
+            };
+
+            for term in tokenize_with(text, &self.config.tokenizer) {
+                terms.push(FieldTerm { field, term });
+            }
+        }
+
+        terms
+    }
+
+    /// Documents matching a `field:term` query (see `parse_field_query`),
+    /// best first.
+    ///
+    /// Each term adds its field's boost when it hits: title hits count
+    /// double. A bare term takes whichever of its fields scores higher. Ties
+    /// follow the index's usual result order.
+    pub fn search_fields(&self, query: &str) -> Vec<(DocumentId, f32)> {
+        let terms = self.parse_field_query(query);
+        let mut scores: HashMap<DocumentId, f32> = HashMap::new();
+
+        // 1. Title tokens per document, only if some term can hit a title
+        let titles: HashMap<DocumentId, HashSet<String>> =
+            if terms.iter().any(|t| t.field != Some(Field::Body)) {
+                self.documents
+                    .values()
+                    .map(|doc| {
+                        let file = doc
+                            .chunk
+                            .as_ref()
+                            .map_or(&doc.path, |info| &info.parent_path);
+                        let stem = file
+                            .file_stem()
+                            .map(|s| s.to_string_lossy())
+                            .unwrap_or_default();
+                        let tokens = tokenize_with(&stem, &self.config.tokenizer);
+                        (doc.id, tokens.into_iter().collect())
+                    })
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+
+        // 2. Best field score for each term, summed per document
+        for FieldTerm { field, term } in &terms {
+            let mut hits: HashMap<DocumentId, f32> = HashMap::new();
+
+            if *field != Some(Field::Title) {
+                for id in self.postings.get(term).into_iter().flatten() {
+                    hits.insert(*id, Field::Body.boost());
+                }
+            }
+
+            if *field != Some(Field::Body) {
+                for (id, title) in &titles {
+                    if title.contains(term) {
+                        let best = hits.entry(*id).or_default();
+                        *best = best.max(Field::Title.boost());
+                    }
+                }
+            }
+
+            for (id, score) in hits {
+                *scores.entry(id).or_default() += score;
+            }
+        }
+
+        self.sort_scored(scores.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+
+    fn ids(results: Vec<(DocumentId, f32)>) -> Vec<DocumentId> {
+        results.into_iter().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn parse_recognizes_known_fields() {
+        let index = Index::new();
+
+        assert_eq!(
+            index.parse_field_query("Title:Rust async http://x"),
+            vec![
+                FieldTerm {
+                    field: Some(Field::Title),
+                    term: "rust".to_string(),
+                },
+                FieldTerm {
+                    field: None,
+                    term: "async".to_string(),
+                },
+                // Unknown prefixes are just text
+                FieldTerm {
+                    field: None,
+                    term: "http".to_string(),
+                },
+                FieldTerm {
+                    field: None,
+                    term: "x".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn title_field_only_matches_titles() {
+        let mut index = Index::new();
+        let titled = test_doc("notes/rust.md", "ownership and borrowing");
+        let mentioned = test_doc("notes/misc.md", "a note that mentions rust");
+        let (titled_id, mentioned_id) = (titled.id, mentioned.id);
+        index.add_documents([titled, mentioned]);
+
+        assert_eq!(ids(index.search_fields("title:rust")), vec![titled_id]);
+        assert_eq!(ids(index.search_fields("body:rust")), vec![mentioned_id]);
+
+        // Bare terms search both, title hits weigh more
+        assert_eq!(
+            index.search_fields("rust"),
+            vec![(titled_id, TITLE_BOOST), (mentioned_id, BODY_BOOST)]
+        );
+    }
+
+    #[test]
+    fn mixed_field_query_sums_per_term() {
+        let mut index = Index::new();
+        let both = test_doc("rust.md", "async runtimes");
+        let title_only = test_doc("rust-basics.md", "ownership");
+        let body_only = test_doc("misc.md", "async rust");
+        let (both_id, title_id, body_id) = (both.id, title_only.id, body_only.id);
+        index.add_documents([both, title_only, body_only]);
+
+        assert_eq!(
+            index.search_fields("title:rust body:async"),
+            vec![
+                (both_id, TITLE_BOOST + BODY_BOOST),
+                (title_id, TITLE_BOOST),
+                (body_id, BODY_BOOST),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Typo-tolerant search.
+//!
+//! Comparing the query against every vocabulary entry costs
+//! O(vocabulary × term length). Instead a Levenshtein automaton for the
+//! query term is run over the sorted term dictionary: its state after each
+//! character is one row of the edit-distance table, so terms sharing a prefix
+//! share that work, and as soon as a prefix can no longer end within
+//! `max_edits` every term starting with it is skipped in one range seek.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize_with;
+use std::collections::HashSet;
+use std::ops::Bound;
+
+impl Index {
+    /// Returns every document containing a term within `max_edits`
+    /// insertions, deletions or substitutions of any query token.
+    ///
+    /// Ordered like `search_query`; `max_edits = 0` is an exact search.
+    pub fn search_fuzzy(&self, query: &str, max_edits: usize) -> Vec<DocumentId> {
+        let mut matched_terms = Vec::new();
+        let mut doc_ids = HashSet::new();
+
+        for token in tokenize_with(query, &self.config.tokenizer) {
+            for term in self.fuzzy_terms(&token, max_edits) {
+                doc_ids.extend(self.postings[term].iter().copied());
+                matched_terms.push(term.to_string());
+            }
+        }
+
+        let mut results: Vec<DocumentId> = doc_ids.into_iter().collect();
+        self.order_results(&mut results, &matched_terms);
+        results
+    }
+
+    /// Indexed terms within `max_edits` of `term`, in sorted order.
+    pub fn fuzzy_terms(&self, term: &str, max_edits: usize) -> Vec<&str> {
+        let automaton = LevenshteinAutomaton::new(term, max_edits);
+        let mut matches = Vec::new();
+
+        // rows[k] is the automaton state after the first k chars of the
+        // previous candidate, reused for the prefix the next one shares
+        let mut rows = vec![automaton.start()];
+        let mut previous: Vec<char> = Vec::new();
+        let mut terms = self.postings.range::<str, _>(..);
+
+        while let Some((candidate, _)) = terms.next() {
+            let chars: Vec<char> = candidate.chars().collect();
+
+            // 1. Rewind to the longest prefix shared with the last candidate
+            let shared = chars
+                .iter()
+                .zip(&previous)
+                .take_while(|(a, b)| a == b)
+                .count()
+                .min(rows.len() - 1);
+            rows.truncate(shared + 1);
+
+            // 2. Feed the rest, stopping early once the prefix is hopeless
+            let mut dead_prefix = None;
+            for (at, ch) in chars.iter().enumerate().skip(shared) {
+                let row = automaton.step(&rows[at], *ch);
+                let dead = !automaton.can_match(&row);
+                rows.push(row);
+
+                if dead {
+                    dead_prefix = Some(at + 1);
+                    break;
+                }
+            }
+            previous = chars;
+
+            match dead_prefix {
+                // 3. Nothing starting with this prefix can match: seek past
+                //    all of them (char::MAX sorts after any real next char)
+                Some(len) => {
+                    let mut skip_to: String = previous[..len].iter().collect();
+                    skip_to.push(char::MAX);
+                    terms = self
+                        .postings
+                        .range::<str, _>((Bound::Excluded(skip_to.as_str()), Bound::Unbounded));
+                }
+                None if automaton.is_match(&rows[previous.len()]) => {
+                    matches.push(candidate.as_str())
+                }
+                None => {}
+            }
+        }
+
+        matches
+    }
+}
+
+// Simulates the Levenshtein NFA for one term. A state is the row of the
+// classic edit-distance table: `row[i]` is the distance between the input
+// read so far and the first `i` chars of the term.
+struct LevenshteinAutomaton {
+    term: Vec<char>,
+    max_edits: usize,
+}
+
+impl LevenshteinAutomaton {
+    fn new(term: &str, max_edits: usize) -> Self {
+        LevenshteinAutomaton {
+            term: term.chars().collect(),
+            max_edits,
+        }
+    }
+
+    fn start(&self) -> Vec<usize> {
+        (0..=self.term.len()).collect()
+    }
+
+    fn step(&self, row: &[usize], ch: char) -> Vec<usize> {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(row[0] + 1);
+
+        for (i, term_ch) in self.term.iter().enumerate() {
+            let substitution = row[i] + usize::from(*term_ch != ch);
+            let insertion = next[i] + 1;
+            let deletion = row[i + 1] + 1;
+            next.push(substitution.min(insertion).min(deletion));
+        }
+
+        next
+    }
+
+    fn is_match(&self, row: &[usize]) -> bool {
+        row[self.term.len()] <= self.max_edits
+    }
+
+    // Distances never shrink as input is added, so once every cell is over
+    // budget no continuation can match.
+    fn can_match(&self, row: &[usize]) -> bool {
+        row.iter().any(|distance| *distance <= self.max_edits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+
+    // Plain dynamic-programming distance, the obviously-correct reference
+    fn levenshtein(a: &str, b: &str) -> usize {
+        let a: Vec<char> = a.chars().collect();
+        let mut row: Vec<usize> = (0..=a.len()).collect();
+
+        for (j, b_ch) in b.chars().enumerate() {
+            let mut next = vec![j + 1];
+            for (i, a_ch) in a.iter().enumerate() {
+                next.push(
+                    (row[i] + usize::from(*a_ch != b_ch))
+                        .min(next[i] + 1)
+                        .min(row[i + 1] + 1),
+                );
+            }
+            row = next;
+        }
+
+        row[a.len()]
+    }
+
+    #[test]
+    fn fuzzy_terms_match_a_naive_scan() {
+        let mut index = Index::new();
+        index.add_document(test_doc(
+            "vocab.txt",
+            "rust rusty trust crust bust rest roast ru r rustacean ownership owner own",
+        ));
+
+        let vocabulary: Vec<String> = index.postings.keys().cloned().collect();
+
+        for query in ["rust", "owner", "ru", "x", ""] {
+            for max_edits in 0..=2 {
+                let expected: Vec<&str> = vocabulary
+                    .iter()
+                    .filter(|term| levenshtein(query, term) <= max_edits)
+                    .map(|term| term.as_str())
+                    .collect();
+
+                assert_eq!(
+                    index.fuzzy_terms(query, max_edits),
+                    expected,
+                    "{query:?} within {max_edits}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn search_fuzzy_tolerates_typos() {
+        let mut index = Index::new();
+        let rust = test_doc("a.txt", "learning rust");
+        let trust = test_doc("b.txt", "trust issues");
+        let garden = test_doc("c.txt", "gardening notes");
+        let (rust_id, trust_id) = (rust.id, trust.id);
+
+        index.add_document(rust);
+        index.add_document(trust);
+        index.add_document(garden);
+
+        assert_eq!(index.search_fuzzy("rsut", 0), Vec::<DocumentId>::new());
+        assert_eq!(index.search_fuzzy("rsut", 2), vec![rust_id]);
+        assert_eq!(index.search_fuzzy("rust", 1), vec![rust_id, trust_id]);
+        assert_eq!(index.search_fuzzy("rust", 0), index.search_query("rust"));
+    }
+}
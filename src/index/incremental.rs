@@ -0,0 +1,249 @@
+//! Saving only what changed since the last save.
+//!
+//! Per-document data (the document itself, its tokens, frequencies,
+//! positions, length and indexing time) makes up most of an index file. Each
+//! add/remove marks the document's id dirty, and `save_incremental` patches
+//! just those entries into the JSON already on disk. The shared structures
+//! (postings, path lookup, synonyms, config) are small enough by comparison
+//! that they're always written in full.
+//!
+//! The dirty set is only meaningful against the file it was last synced
+//! with (loaded from or incrementally saved to), so that path is recorded
+//! too. Anywhere else gets a full save instead of a patch.
+
+use super::{Index, IndexError};
+use crate::ingestion::{DocumentId, normalize_path};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ids of documents added, changed or removed since the index was last
+/// synced with `synced_with`. Not part of the index's data: skipped by serde
+/// and always equal.
+#[derive(Debug, Default)]
+pub(super) struct DirtySet {
+    ids: HashSet<DocumentId>,
+    // The file that holds everything not in `ids`, if any
+    synced_with: Option<PathBuf>,
+}
+
+impl DirtySet {
+    pub(super) fn mark(&mut self, id: DocumentId) {
+        self.ids.insert(id);
+    }
+
+    /// Records that the file at `path` now holds the whole index.
+    pub(super) fn synced(&mut self, path: &Path) {
+        self.ids.clear();
+        self.synced_with = Some(normalize_path(path));
+    }
+
+    fn is_synced_with(&self, path: &Path) -> bool {
+        self.synced_with.as_deref() == Some(normalize_path(path).as_path())
+    }
+}
+
+impl PartialEq for DirtySet {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Index {
+    /// Like `save_to_disk`, but only re-serializes documents that changed
+    /// since the last sync, merging them into the file at `path`.
+    ///
+    /// Patching only happens when `path` is the file this index was loaded
+    /// from (`load_from_disk`) or last saved to with `save_incremental`;
+    /// unchanged documents are then copied over as is (the file is still
+    /// rewritten as a whole). Any other path, including one written by
+    /// `save_to_disk`, gets a full save first, since its contents can't be
+    /// trusted to match. The file keeps its pretty or compact formatting
+    /// (see `save_to_disk_compact`); a new file is pretty.
+    pub fn save_incremental<P: AsRef<Path>>(&mut self, path: P) -> Result<(), IndexError> {
+        let path = path.as_ref();
+        let existing = match fs::read_to_string(path) {
+            Ok(json) => Some(json),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        // Pretty JSON puts a newline right after the opening brace
+        let compact = existing
+            .as_deref()
+            .is_some_and(|json| !json.starts_with("{\n"));
+
+        // 1. Start from what's on disk, if it's known to be ours
+        let mut on_disk: Map<String, Value> = match existing {
+            Some(json) if self.dirty.is_synced_with(path) => serde_json::from_str(&json)?,
+            _ => {
+                if compact {
+                    self.save_to_disk_compact(path)?;
+                } else {
+                    self.save_to_disk(path)?;
+                }
+                self.dirty.synced(path);
+                return Ok(());
+            }
+        };
+
+        // 2. Shared structures are rewritten wholesale
+        on_disk.insert("version".into(), serde_json::to_value(self.version)?);
+        on_disk.insert("postings".into(), serde_json::to_value(&self.postings)?);
+        on_disk.insert(
+            "path_to_ids".into(),
+            serde_json::to_value(&self.path_to_ids)?,
+        );
+        on_disk.remove("path_to_id");
+        on_disk.insert("synonyms".into(), serde_json::to_value(&self.synonyms)?);
+        on_disk.insert("config".into(), serde_json::to_value(&self.config)?);
+
+        // 3. Per-document maps only get their dirty entries replaced
+        for id in &self.dirty.ids {
+            let key = id.to_string();
+            patch(&mut on_disk, "documents", &key, self.documents.get(id))?;
+            patch(&mut on_disk, "doc_tokens", &key, self.doc_tokens.get(id))?;
+            patch(&mut on_disk, "term_freqs", &key, self.term_freqs.get(id))?;
+            patch(&mut on_disk, "positions", &key, self.positions.get(id))?;
+            patch(&mut on_disk, "doc_lengths", &key, self.doc_lengths.get(id))?;
+            patch(&mut on_disk, "indexed_at", &key, self.indexed_at.get(id))?;
+        }
+
+        let json = if compact {
+            serde_json::to_string(&on_disk)?
+        } else {
+            serde_json::to_string_pretty(&on_disk)?
+        };
+        fs::write(path, json)?;
+        self.dirty.synced(path);
+        Ok(())
+    }
+}
+
+// Sets (or, for a removed document, deletes) `key` in the map `field`.
+fn patch<T: Serialize>(
+    on_disk: &mut Map<String, Value>,
+    field: &str,
+    key: &str,
+    value: Option<&T>,
+) -> Result<(), IndexError> {
+    let map = on_disk
+        .entry(field)
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or(IndexError::InvalidFormat("index file field is not a map"))?;
+
+    match value {
+        Some(value) => {
+            map.insert(key.to_string(), serde_json::to_value(value)?);
+        }
+        None => {
+            map.remove(key);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn read_json(path: &Path) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn only_the_changed_document_is_rewritten() {
+        let path = std::env::temp_dir().join(format!("incremental_{}.json", Uuid::new_v4()));
+
+        let mut index = Index::new();
+        let kept = index.add_text("untouched document");
+        let removed = index.add_text("going away");
+        index.save_incremental(&path).unwrap();
+        let before = read_json(&path);
+
+        // One change: a new document and a removal
+        let added = index.add_text("freshly added");
+        index.remove_document(removed);
+        assert_eq!(index.dirty.ids.len(), 2);
+        index.save_incremental(&path).unwrap();
+        let after = read_json(&path);
+
+        // The untouched document's entries are exactly what was there
+        let kept = kept.to_string();
+        for field in [
+            "documents",
+            "doc_tokens",
+            "term_freqs",
+            "positions",
+            "indexed_at",
+        ] {
+            assert_eq!(after[field][&kept], before[field][&kept], "{field}");
+            assert!(after[field].get(removed.to_string()).is_none(), "{field}");
+            assert!(after[field].get(added.to_string()).is_some(), "{field}");
+        }
+
+        // And the merged file loads back as the same index
+        assert!(index.dirty.ids.is_empty());
+        assert_eq!(Index::load_from_disk(&path).unwrap(), index);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn compact_files_stay_compact() {
+        let path = std::env::temp_dir().join(format!("incremental_{}.json", Uuid::new_v4()));
+
+        let mut index = Index::new();
+        index.add_text("compact document");
+        index.save_to_disk_compact(&path).unwrap();
+
+        index.add_text("another one");
+        index.save_incremental(&path).unwrap();
+        index.add_text("and a third");
+        index.save_incremental(&path).unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(!json.contains('\n'));
+        assert_eq!(Index::load_from_disk(&path).unwrap(), index);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn only_the_synced_file_is_patched() {
+        let dir = std::env::temp_dir();
+        let ours = dir.join(format!("incremental_{}.json", Uuid::new_v4()));
+        let other = dir.join(format!("incremental_{}.json", Uuid::new_v4()));
+
+        let mut index = Index::new();
+        index.add_text("first document");
+        index.save_incremental(&ours).unwrap();
+
+        // A file some other index wrote, with documents we don't have
+        let mut stranger = Index::new();
+        stranger.add_text("stale stranger");
+        stranger.save_to_disk(&other).unwrap();
+
+        // Not patched into: nothing of the stranger survives
+        index.add_text("second document");
+        index.save_incremental(&other).unwrap();
+        let loaded = Index::load_from_disk(&other).unwrap();
+        assert!(loaded.search_query("stranger").is_empty());
+        assert_eq!(loaded, index);
+
+        // A file loaded from is synced and can be patched straight away
+        let mut reloaded = Index::load_from_disk(&ours).unwrap();
+        assert!(reloaded.dirty.is_synced_with(&ours));
+        reloaded.add_text("third document");
+        reloaded.save_incremental(&ours).unwrap();
+        assert_eq!(Index::load_from_disk(&ours).unwrap(), reloaded);
+
+        fs::remove_file(ours).unwrap();
+        fs::remove_file(other).unwrap();
+    }
+}
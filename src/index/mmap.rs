@@ -0,0 +1,476 @@
+//! Read-only, memory-mapped storage for an `Index`.
+//!
+//! The JSON format has to be parsed in full before the first query. This
+//! layout keeps terms sorted in a fixed-width table so a lookup is a binary
+//! search over the mapped bytes, and only the matching postings slice is read.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! header    MAGIC (8) | version u32 | doc_count u32 | term_count u32
+//! docs      doc_count  x [ uuid (16) | path_offset u32 | path_len u32 ]
+//! terms     term_count x [ term_offset u32 | term_len u32 | postings_offset u32 | postings_len u32 ]
+//! postings  u32 document ordinals, sorted, one run per term
+//! strings   UTF-8 bytes for terms and paths
+//! ```
+//!
+//! Documents are stored sorted by (path, id), so ordinals sort the same way
+//! `search_query` does and a union of ordinals is already in result order.
+//!
+//! `IndexView` does the actual reading over any byte slice in this layout;
+//! `MmapIndex` just owns the mapping it borrows from.
+
+use super::{Index, IndexError};
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize;
+use memmap2::Mmap;
+use std::collections::{BTreeSet, HashMap};
+use std::fs::{self, File};
+use std::path::Path;
+use uuid::Uuid;
+
+const MAGIC: &[u8; 8] = b"PKSEMMAP";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 8 + 4 + 4 + 4;
+const DOC_ENTRY_LEN: usize = 16 + 4 + 4;
+const TERM_ENTRY_LEN: usize = 4 * 4;
+
+impl Index {
+    /// Writes the postings and document paths in the memory-mappable layout
+    /// read by `Index::open_mmap`. Document content is not stored.
+    pub fn save_mmap<P: AsRef<Path>>(&self, path: P) -> Result<(), IndexError> {
+        // 1. Fix a document order: by path, then id (same as search_query)
+        let mut doc_ids: Vec<DocumentId> = self.documents.keys().copied().collect();
+        self.sort_by_path(&mut doc_ids);
+
+        let ordinals: HashMap<DocumentId, u32> = doc_ids
+            .iter()
+            .enumerate()
+            .map(|(ordinal, id)| (*id, ordinal as u32))
+            .collect();
+
+        // 2. Terms must be sorted for the binary search on read
+        let mut terms: Vec<&String> = self.postings.keys().collect();
+        terms.sort();
+
+        let docs_start = HEADER_LEN;
+        let terms_start = docs_start + doc_ids.len() * DOC_ENTRY_LEN;
+        let postings_start = terms_start + terms.len() * TERM_ENTRY_LEN;
+        let posting_total: usize = self.postings.values().map(|ids| ids.len()).sum();
+        let strings_start = postings_start + posting_total * 4;
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        let mut doc_table = Vec::with_capacity(doc_ids.len() * DOC_ENTRY_LEN);
+        let mut term_table = Vec::with_capacity(terms.len() * TERM_ENTRY_LEN);
+        let mut postings = Vec::with_capacity(posting_total * 4);
+        let mut strings = Vec::new();
+
+        header.extend_from_slice(MAGIC);
+        push_u32(&mut header, VERSION);
+        push_u32(&mut header, to_u32(doc_ids.len())?);
+        push_u32(&mut header, to_u32(terms.len())?);
+
+        // 3. Document table + path strings
+        for doc_id in &doc_ids {
+            let path = self.documents[doc_id].path.to_string_lossy();
+
+            doc_table.extend_from_slice(doc_id.as_uuid().as_bytes());
+            push_u32(&mut doc_table, to_u32(strings_start + strings.len())?);
+            push_u32(&mut doc_table, to_u32(path.len())?);
+            strings.extend_from_slice(path.as_bytes());
+        }
+
+        // 4. Term table + sorted ordinal runs + term strings
+        for term in terms {
+            let mut run: Vec<u32> = self.postings[term]
+                .iter()
+                .filter_map(|doc_id| ordinals.get(doc_id).copied())
+                .collect();
+            run.sort_unstable();
+
+            push_u32(&mut term_table, to_u32(strings_start + strings.len())?);
+            push_u32(&mut term_table, to_u32(term.len())?);
+            push_u32(&mut term_table, to_u32(postings_start + postings.len())?);
+            push_u32(&mut term_table, to_u32(run.len())?);
+            strings.extend_from_slice(term.as_bytes());
+
+            for ordinal in run {
+                push_u32(&mut postings, ordinal);
+            }
+        }
+
+        let mut bytes = header;
+        bytes.extend(doc_table);
+        bytes.extend(term_table);
+        bytes.extend(postings);
+        bytes.extend(strings);
+
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Opens a file written by `save_mmap` as a read-only searchable view.
+    ///
+    /// The header, tables and postings are validated up front (one pass
+    /// over the ordinals); term and path strings are read from the mapping on
+    /// demand, so nothing is copied out of it.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<MmapIndex, IndexError> {
+        let file = File::open(path)?;
+
+        // SAFETY: the mapping is read-only and we never hand out references
+        // that outlive `MmapIndex`. Like every mmap user we rely on the file
+        // not being truncated by another process while it's mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        MmapIndex::from_mmap(mmap)
+    }
+}
+
+/// A read-only index backed by a memory-mapped file from `Index::save_mmap`.
+pub struct MmapIndex {
+    mmap: Mmap,
+    doc_count: usize,
+    term_count: usize,
+}
+
+impl MmapIndex {
+    fn from_mmap(mmap: Mmap) -> Result<Self, IndexError> {
+        let view = IndexView::new(&mmap)?;
+        let (doc_count, term_count) = (view.doc_count, view.term_count);
+
+        Ok(MmapIndex {
+            mmap,
+            doc_count,
+            term_count,
+        })
+    }
+
+    /// Borrowing view over the mapping; see `IndexView`.
+    pub fn view(&self) -> IndexView<'_> {
+        IndexView {
+            bytes: &self.mmap,
+            doc_count: self.doc_count,
+            term_count: self.term_count,
+        }
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.doc_count
+    }
+
+    /// Same semantics and ordering as `Index::search_query`. Queries go
+    /// through the default tokenizer; synonyms aren't stored in this format.
+    pub fn search_query(&self, query: &str) -> Vec<DocumentId> {
+        self.view().search_query(query)
+    }
+
+    /// Path of a document by id, or `None` if it isn't in the file.
+    pub fn document_path(&self, id: DocumentId) -> Option<&str> {
+        self.view().document_path(id)
+    }
+}
+
+/// Zero-copy, read-only index over bytes in the `save_mmap` layout, e.g. a
+/// mapped file or a buffer read into memory once.
+///
+/// Terms and paths are `&str`s pointing into the buffer and postings are
+/// decoded straight from it, so nothing is copied into owned `String`s.
+/// `Copy`, so hand it to as many searching threads as needed.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexView<'a> {
+    bytes: &'a [u8],
+    doc_count: usize,
+    term_count: usize,
+}
+
+impl<'a> IndexView<'a> {
+    /// Checks the header and table sizes, and that every posting run is in
+    /// bounds and only refers to stored documents, so corrupt or truncated
+    /// input is an error here rather than a panic while searching. Term and
+    /// path strings are only read when needed.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, IndexError> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            return Err(IndexError::InvalidFormat("missing mmap index header"));
+        }
+
+        if read_u32(bytes, 8) != VERSION {
+            return Err(IndexError::InvalidFormat("unsupported mmap index version"));
+        }
+
+        let doc_count = read_u32(bytes, 12) as usize;
+        let term_count = read_u32(bytes, 16) as usize;
+
+        let tables_end = HEADER_LEN + doc_count * DOC_ENTRY_LEN + term_count * TERM_ENTRY_LEN;
+        if bytes.len() < tables_end {
+            return Err(IndexError::InvalidFormat("mmap index is truncated"));
+        }
+
+        let view = IndexView {
+            bytes,
+            doc_count,
+            term_count,
+        };
+
+        // Searches turn ordinals into document table entries unchecked
+        for at in 0..term_count {
+            let entry = view.term_entry(at);
+            let start = read_u32(bytes, entry + 8) as usize;
+            let len = read_u32(bytes, entry + 12) as usize;
+
+            let run = bytes
+                .get(start..start + len * 4)
+                .ok_or(IndexError::InvalidFormat("mmap postings out of bounds"))?;
+            if run
+                .chunks_exact(4)
+                .any(|b| read_u32(b, 0) as usize >= doc_count)
+            {
+                return Err(IndexError::InvalidFormat(
+                    "mmap posting refers to a missing document",
+                ));
+            }
+        }
+
+        Ok(view)
+    }
+
+    pub fn document_count(&self) -> usize {
+        self.doc_count
+    }
+
+    /// Every indexed term, sorted, borrowed from the buffer.
+    pub fn terms(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let view = *self;
+        (0..self.term_count).filter_map(move |at| view.str_at(view.term_entry(at)))
+    }
+
+    /// Documents containing any query token, ordered like
+    /// `Index::search_query`.
+    pub fn search_query(&self, query: &str) -> Vec<DocumentId> {
+        let mut ordinals = BTreeSet::new();
+
+        for token in tokenize(query) {
+            if let Some(run) = self.postings_for(&token) {
+                ordinals.extend(run);
+            }
+        }
+
+        ordinals
+            .into_iter()
+            .map(|ordinal| self.doc_id(ordinal as usize))
+            .collect()
+    }
+
+    /// Documents containing every query token, ordered like
+    /// `Index::search_query`. Empty for a query without tokens.
+    pub fn search_all(&self, query: &str) -> Vec<DocumentId> {
+        let tokens: BTreeSet<String> = tokenize(query).into_iter().collect();
+
+        // 1. Every token needs a run; any unknown token means no matches
+        let mut runs = Vec::with_capacity(tokens.len());
+        for token in &tokens {
+            match self.postings_for(token) {
+                Some(run) => runs.push(run.collect::<Vec<u32>>()),
+                None => return Vec::new(),
+            }
+        }
+
+        // 2. Walk the shortest run, probing the others (all sorted)
+        runs.sort_by_key(|run| run.len());
+        let Some((shortest, rest)) = runs.split_first() else {
+            return Vec::new();
+        };
+
+        shortest
+            .iter()
+            .filter(|ordinal| rest.iter().all(|run| run.binary_search(ordinal).is_ok()))
+            .map(|ordinal| self.doc_id(*ordinal as usize))
+            .collect()
+    }
+
+    /// Path of a document by id, or `None` if it isn't in the buffer.
+    pub fn document_path(&self, id: DocumentId) -> Option<&'a str> {
+        (0..self.doc_count)
+            .find(|ordinal| self.doc_id(*ordinal) == id)
+            .and_then(|ordinal| {
+                let entry = HEADER_LEN + ordinal * DOC_ENTRY_LEN;
+                self.str_at(entry + 16)
+            })
+    }
+
+    fn term_entry(&self, at: usize) -> usize {
+        HEADER_LEN + self.doc_count * DOC_ENTRY_LEN + at * TERM_ENTRY_LEN
+    }
+
+    // Binary search the sorted term table, returning that term's ordinals.
+    fn postings_for(&self, token: &str) -> Option<impl Iterator<Item = u32> + 'a> {
+        let (mut low, mut high) = (0, self.term_count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let entry = self.term_entry(mid);
+            let term = self.str_at(entry)?;
+
+            match term.cmp(token) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => {
+                    let start = read_u32(self.bytes, entry + 8) as usize;
+                    let len = read_u32(self.bytes, entry + 12) as usize;
+                    let bytes = self.bytes.get(start..start + len * 4)?;
+
+                    return Some(
+                        bytes
+                            .chunks_exact(4)
+                            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+                    );
+                }
+            }
+        }
+
+        None
+    }
+
+    fn doc_id(&self, ordinal: usize) -> DocumentId {
+        let entry = HEADER_LEN + ordinal * DOC_ENTRY_LEN;
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&self.bytes[entry..entry + 16]);
+        DocumentId::from(Uuid::from_bytes(bytes))
+    }
+
+    // Reads an (offset u32, len u32) pair at `at` and returns that string.
+    fn str_at(&self, at: usize) -> Option<&'a str> {
+        let offset = read_u32(self.bytes, at) as usize;
+        let len = read_u32(self.bytes, at + 4) as usize;
+        let bytes = self.bytes.get(offset..offset + len)?;
+        std::str::from_utf8(bytes).ok()
+    }
+}
+
+fn push_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+fn to_u32(value: usize) -> Result<u32, IndexError> {
+    u32::try_from(value).map_err(|_| IndexError::InvalidFormat("index too large for mmap format"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+    use std::path::PathBuf;
+
+    fn temp_file(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}_{}", name, Uuid::new_v4()))
+    }
+
+    #[test]
+    fn mmap_search_matches_owned_index() {
+        let mut index = Index::new();
+
+        for (path, content) in [
+            ("b.md", "rust async runtimes"),
+            ("a.txt", "rust ownership"),
+            ("c.txt", "gardening notes"),
+        ] {
+            index.add_document(test_doc(path, content));
+        }
+
+        let file = temp_file("index.mmap");
+        index.save_mmap(&file).unwrap();
+
+        let view = Index::open_mmap(&file).unwrap();
+        assert_eq!(view.document_count(), 3);
+
+        for query in ["rust", "async gardening", "ownership", "missing", ""] {
+            assert_eq!(view.search_query(query), index.search_query(query));
+        }
+
+        let hit = view.search_query("gardening")[0];
+        assert_eq!(view.document_path(hit), Some("c.txt"));
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn view_searches_borrowed_bytes() {
+        let mut index = Index::new();
+        index.add_text("rust async runtimes");
+        index.add_text("rust ownership");
+        index.add_text("async gardening");
+
+        let file = temp_file("view.mmap");
+        index.save_mmap(&file).unwrap();
+        let bytes = fs::read(&file).unwrap();
+
+        let view = IndexView::new(&bytes).unwrap();
+        assert_eq!(view.document_count(), 3);
+
+        for query in ["rust", "rust async", "async gardening", "missing", ""] {
+            assert_eq!(view.search_query(query), index.search_query(query));
+            assert_eq!(
+                view.search_all(query),
+                index.search_summary(query).all_ids,
+                "{query}"
+            );
+        }
+
+        // Terms are borrowed from the buffer, sorted
+        let terms: Vec<&str> = view.terms().collect();
+        assert_eq!(terms, index.postings.keys().collect::<Vec<_>>());
+
+        assert!(IndexView::new(&bytes[..10]).is_err());
+
+        fs::remove_file(file).unwrap();
+    }
+
+    #[test]
+    fn view_rejects_corrupt_postings() {
+        let mut index = Index::new();
+        index.add_text("rust");
+        index.add_text("async");
+
+        let file = temp_file("corrupt.mmap");
+        index.save_mmap(&file).unwrap();
+        let bytes = fs::read(&file).unwrap();
+        fs::remove_file(file).unwrap();
+
+        // First term's run: point its only ordinal past the document table
+        let entry = HEADER_LEN + 2 * DOC_ENTRY_LEN;
+        let postings = read_u32(&bytes, entry + 8) as usize;
+        let mut corrupt = bytes.clone();
+        corrupt[postings..postings + 4].copy_from_slice(&7u32.to_le_bytes());
+        assert!(matches!(
+            IndexView::new(&corrupt),
+            Err(IndexError::InvalidFormat(_))
+        ));
+
+        // A run that claims more ordinals than the buffer holds
+        let mut corrupt = bytes.clone();
+        corrupt[entry + 12..entry + 16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            IndexView::new(&corrupt),
+            Err(IndexError::InvalidFormat(_))
+        ));
+
+        // Cut off inside the postings
+        assert!(IndexView::new(&bytes[..postings + 2]).is_err());
+        assert!(IndexView::new(&bytes).is_ok());
+    }
+
+    #[test]
+    fn open_mmap_rejects_other_files() {
+        let file = temp_file("not_an_index.json");
+        fs::write(&file, "{}").unwrap();
+
+        assert!(matches!(
+            Index::open_mmap(&file),
+            Err(IndexError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(file).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+//! Newline-delimited JSON interchange for stored documents.
+
+use super::{Index, IndexError};
+use crate::ingestion::{Document, DocumentId, DocumentSource};
+use log::warn;
+use std::borrow::Cow;
+use std::io::{self, BufRead, Write};
+
+impl Index {
+    /// Writes every stored `Document` as one JSON object per line (NDJSON),
+    /// in path order.
+    ///
+    /// Unlike `save_to_disk` this is independent of the internal index
+    /// layout, so it's the format to hand to other tools (`jq`, scripts).
+    ///
+    /// `content` is always the full text: when the index only keeps a
+    /// preview or nothing (see `ContentStorage`) it's re-read from the file,
+    /// so re-importing the export doesn't index a truncated document. If the
+    /// file can't be read any more, whatever is stored is written instead.
+    pub fn export_documents_ndjson<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut doc_ids: Vec<DocumentId> = self.documents.keys().copied().collect();
+        self.sort_by_path(&mut doc_ids);
+
+        for doc_id in doc_ids {
+            let doc = &self.documents[&doc_id];
+
+            // Only a re-read document needs a copy with its content swapped in
+            match self.full_content(doc_id) {
+                Some(Cow::Owned(content)) => serde_json::to_writer(
+                    &mut writer,
+                    &Document {
+                        content,
+                        ..doc.clone()
+                    },
+                )?,
+                _ => serde_json::to_writer(&mut writer, doc)?,
+            }
+            writer.write_all(b"\n")?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads one JSON `Document` per line and upserts each into the index,
+    /// returning how many were imported.
+    ///
+    /// A line replaces whatever is indexed at its path and whatever already
+    /// has its id (e.g. the same document exported after a rename). The
+    /// `lang` hint is kept, so n-gram tokenized documents stay searchable.
+    ///
+    /// Blank lines are ignored. A line that isn't a valid document is skipped
+    /// with a logged warning so one bad record doesn't sink the batch; I/O
+    /// errors stop the import.
+    pub fn import_documents_ndjson<R: BufRead>(&mut self, reader: R) -> Result<usize, IndexError> {
+        let mut imported = 0;
+
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<Document>(&line) {
+                Ok(doc) => {
+                    // Its id may be indexed under another path
+                    if self.documents.contains_key(&doc.id) {
+                        self.remove_document(doc.id);
+                    }
+
+                    self.upsert_document(Document {
+                        source: DocumentSource::Imported,
+                        ..doc
+                    });
+                    imported += 1;
+                }
+                Err(e) => {
+                    warn!("skipping malformed NDJSON line {}: {}", line_number + 1, e);
+                }
+            }
+        }
+
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::ContentStorage;
+    use crate::ingestion::{test_doc, test_doc_modified};
+    use crate::tokenizer::TokenStrategy;
+    use std::path::Path;
+    use std::time::SystemTime;
+
+    #[test]
+    fn export_round_trips_documents() {
+        let mut index = Index::new();
+        let docs = vec![
+            test_doc_modified("b.md", "line one\nline \"two\"", Some(SystemTime::now())),
+            test_doc("a.txt", "plain"),
+        ];
+        index.add_documents(docs.clone());
+
+        let mut out = Vec::new();
+        index.export_documents_ndjson(&mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        // One line per document, embedded newlines stay escaped
+        assert_eq!(lines.len(), 2);
+
+        // Readable RFC 3339 times, not serde's SystemTime struct
+        assert!(lines[0].contains(r#""modified":null"#));
+        assert!(lines[1].contains(r#""modified":"20"#), "{}", lines[1]);
+        assert!(!text.contains("secs_since_epoch"));
+
+        let parsed: Vec<Document> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        // Path order: a.txt first
+        assert_eq!(parsed, vec![docs[1].clone(), docs[0].clone()]);
+    }
+
+    #[test]
+    fn import_adds_documents_and_skips_bad_lines() {
+        let first = DocumentId::random();
+        let second = DocumentId::random();
+
+        let input = format!(
+            "{}\n\nnot json at all\n{}\n",
+            serde_json::json!({
+                "id": first,
+                "path": "scraped/one.md",
+                "content": "scraped rust article",
+                "modified": null
+            }),
+            serde_json::json!({
+                "id": second,
+                "path": "scraped/two.md",
+                "content": "another article",
+                "modified": null
+            }),
+        );
+
+        let mut index = Index::new();
+        let count = index.import_documents_ndjson(input.as_bytes()).unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(index.search_query("rust"), vec![first]);
+        assert_eq!(index.search_query("article"), vec![first, second]);
+    }
+
+    #[test]
+    fn import_of_export_rebuilds_the_index() {
+        let builder = || Index::builder().language("ja", TokenStrategy::NGrams(2));
+        let mut source = builder().build();
+        source.add_text("exported words");
+        source.add_text("more exported words");
+        source.add_document(Document {
+            lang: Some("ja".to_string()),
+            ..test_doc("ja.md", "東京旅行のメモ")
+        });
+
+        let mut ndjson = Vec::new();
+        source.export_documents_ndjson(&mut ndjson).unwrap();
+
+        let mut target = builder().build();
+        assert_eq!(target.import_documents_ndjson(&ndjson[..]).unwrap(), 3);
+        assert_eq!(target.postings, source.postings);
+        assert_eq!(target.path_to_ids, source.path_to_ids);
+
+        // Same documents, now labelled as imported
+        for (id, doc) in &target.documents {
+            assert_eq!(doc.source, DocumentSource::Imported);
+            assert_eq!(doc.content, source.documents[id].content);
+            assert_eq!(doc.lang, source.documents[id].lang);
+        }
+
+        // The language hint survived, so the n-grams are still there
+        assert_eq!(target.search_query("旅行").len(), 1);
+
+        // Importing again upserts by path instead of duplicating
+        assert_eq!(target.import_documents_ndjson(&ndjson[..]).unwrap(), 3);
+        assert_eq!(target.search_query("exported").len(), 2);
+        assert_eq!(target.verify(), Ok(()));
+    }
+
+    #[test]
+    fn import_replaces_a_document_with_the_same_id_at_another_path() {
+        let id = DocumentId::random();
+        let line = |path: &str, content: &str| {
+            serde_json::json!({ "id": id, "path": path, "content": content, "modified": null })
+                .to_string()
+        };
+
+        let mut index = Index::new();
+        index
+            .import_documents_ndjson(line("a.md", "alpha").as_bytes())
+            .unwrap();
+        index
+            .import_documents_ndjson(line("b.md", "beta").as_bytes())
+            .unwrap();
+
+        assert_eq!(index.document_count(), 1);
+        assert!(index.search_query("alpha").is_empty());
+        assert_eq!(index.search_query("beta"), vec![id]);
+        assert!(index.ids_for_path(Path::new("a.md")).is_empty());
+        assert_eq!(index.ids_for_path(Path::new("b.md")), vec![id]);
+        assert_eq!(index.verify(), Ok(()));
+    }
+
+    #[test]
+    fn export_writes_full_content_when_only_a_preview_is_stored() {
+        let dir = std::env::temp_dir().join(format!("ndjson_storage_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        std::fs::write(&path, "notes about rust ownership").unwrap();
+        let doc = crate::ingestion::load_document(&path).unwrap();
+
+        for storage in [ContentStorage::Preview(5), ContentStorage::None] {
+            let mut index = Index::with_content_storage(storage);
+            index.add_document(doc.clone());
+
+            let mut out = Vec::new();
+            index.export_documents_ndjson(&mut out).unwrap();
+            let exported: Document = serde_json::from_slice(&out).unwrap();
+            assert_eq!(exported.content, "notes about rust ownership");
+
+            // And the round trip indexes all of it
+            let mut imported = Index::new();
+            imported.import_documents_ndjson(out.as_slice()).unwrap();
+            assert_eq!(imported.search_query("ownership").len(), 1);
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,254 @@
+//! Matching vocabulary terms by regular expression, `*`/`?` glob, or as a
+//! prefix while the user is still typing.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize_with;
+use regex::RegexBuilder;
+use std::collections::HashSet;
+use std::ops::Bound;
+
+// Upper bound on the compiled program. The regex crate never backtracks, so
+// the risk is a pattern like `\w{1000}{1000}` blowing up at compile time.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+impl Index {
+    /// Returns every document containing a term matched by `pattern`, e.g.
+    /// `^colou?r$`. Terms are lowercase, so write patterns in lowercase.
+    ///
+    /// The pattern is tested against each indexed term on its own, not the
+    /// document text, and results are ordered like `search_query`. Invalid
+    /// or oversized patterns return the `regex::Error`.
+    pub fn search_regex(&self, pattern: &str) -> Result<Vec<DocumentId>, regex::Error> {
+        let regex = RegexBuilder::new(pattern)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .dfa_size_limit(REGEX_SIZE_LIMIT)
+            .build()?;
+
+        let mut matched_terms = Vec::new();
+        let mut doc_ids = HashSet::new();
+
+        for (term, ids) in &self.postings {
+            if regex.is_match(term) {
+                doc_ids.extend(ids.iter().copied());
+                matched_terms.push(term.clone());
+            }
+        }
+
+        let mut results: Vec<DocumentId> = doc_ids.into_iter().collect();
+        self.order_results(&mut results, &matched_terms);
+        Ok(results)
+    }
+
+    /// Returns every document containing a term matched by a glob like
+    /// `rust*` or `te?t`: `*` is any run of characters (including none) and
+    /// `?` exactly one. Everything else is literal and case-insensitive.
+    ///
+    /// Only terms starting with the part before the first wildcard are
+    /// looked at, so `rust*` is a cheap range scan. Without wildcards this
+    /// is an exact term lookup. Results are ordered like `search_query`.
+    pub fn search_glob(&self, pattern: &str) -> Vec<DocumentId> {
+        let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+        let prefix: String = pattern
+            .iter()
+            .take_while(|ch| !matches!(ch, '*' | '?'))
+            .collect();
+
+        let mut matched_terms = Vec::new();
+        let mut doc_ids = HashSet::new();
+
+        // 1. The sorted vocabulary puts every candidate right after `prefix`
+        for (term, ids) in self
+            .postings
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+        {
+            if !term.starts_with(&prefix) {
+                break;
+            }
+
+            let term_chars: Vec<char> = term.chars().collect();
+            if glob_match(&pattern, &term_chars) {
+                doc_ids.extend(ids.iter().copied());
+                matched_terms.push(term.clone());
+            }
+        }
+
+        let mut results: Vec<DocumentId> = doc_ids.into_iter().collect();
+        self.order_results(&mut results, &matched_terms);
+        results
+    }
+
+    /// Search-as-you-type: every complete word of `query` is an exact term
+    /// and the last, still being typed, a prefix, all OR-ed together. So
+    /// "rust as" finds documents with "rust" or any word starting with "as".
+    ///
+    /// A query ending in whitespace has no partial word and behaves exactly
+    /// like `search_query`. Results are ordered like `search_query`.
+    pub fn search_incremental(&self, query: &str) -> Vec<DocumentId> {
+        // 1. Nothing being typed right now: a plain search
+        if query.is_empty() || query.ends_with(char::is_whitespace) {
+            return self.search_query(query);
+        }
+
+        let (complete, partial) = match query.rsplit_once(char::is_whitespace) {
+            Some((complete, partial)) => (complete, partial),
+            None => ("", query),
+        };
+
+        // 2. Complete words (plus synonyms) exactly, like search_query. A
+        //    partial word can still hold several tokens ("foo-ba"); only the
+        //    last of them is unfinished.
+        let mut terms = self.query_tokens(complete);
+        let mut partial_tokens = tokenize_with(partial, &self.config.tokenizer);
+        let prefix = partial_tokens.pop();
+        terms.extend(partial_tokens);
+
+        let mut doc_ids: HashSet<DocumentId> = terms
+            .iter()
+            .filter_map(|term| self.postings.get(term))
+            .flatten()
+            .copied()
+            .collect();
+
+        // 3. Every vocabulary term starting with the prefix
+        if let Some(prefix) = prefix {
+            for (term, ids) in self
+                .postings
+                .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            {
+                if !term.starts_with(&prefix) {
+                    break;
+                }
+
+                doc_ids.extend(ids.iter().copied());
+                terms.push(term.clone());
+            }
+        }
+
+        let mut results: Vec<DocumentId> = doc_ids.into_iter().collect();
+        self.order_results(&mut results, &terms);
+        results
+    }
+}
+
+// Wildcard match over chars. On a mismatch after a `*`, retry with the star
+// swallowing one more char; linear-ish and never exponential.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(ch) if *ch == '?' || *ch == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // Trailing stars match the empty rest
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchored_pattern_matches_spelling_variants() {
+        let mut index = Index::new();
+        let us = index.add_text("favorite color");
+        let uk = index.add_text("favourite colour");
+        index.add_text("colorful discolor");
+
+        let mut expected = vec![us, uk];
+        index.sort_by_path(&mut expected);
+
+        assert_eq!(index.search_regex("^colou?r$").unwrap(), expected);
+        assert!(index.search_regex("^xyz").unwrap().is_empty());
+    }
+
+    #[test]
+    fn glob_matches_star_and_question_mark() {
+        let mut index = Index::new();
+        let rust = index.add_text("rust");
+        let rustacean = index.add_text("Rustacean");
+        let trust = index.add_text("trust");
+        let test = index.add_text("test");
+        let text = index.add_text("text");
+        let tent = index.add_text("tent");
+
+        let sorted = |mut ids: Vec<DocumentId>| {
+            index.sort_by_path(&mut ids);
+            ids
+        };
+
+        assert_eq!(index.search_glob("rust*"), sorted(vec![rust, rustacean]));
+        assert_eq!(index.search_glob("te?t"), sorted(vec![test, text, tent]));
+        assert_eq!(index.search_glob("te*t"), sorted(vec![test, text, tent]));
+        assert_eq!(index.search_glob("*ust"), sorted(vec![rust, trust]));
+        assert_eq!(index.search_glob("tex?"), vec![text]);
+
+        // No wildcards: exact lookup, case-insensitive
+        assert_eq!(index.search_glob("RUST"), vec![rust]);
+        assert!(index.search_glob("rus").is_empty());
+        assert_eq!(index.search_glob("*").len(), 6);
+    }
+
+    #[test]
+    fn invalid_and_oversized_patterns_are_errors() {
+        let index = Index::new();
+
+        assert!(index.search_regex("colou?r(").is_err());
+        assert!(matches!(
+            index.search_regex(r"\w{1000}{1000}"),
+            Err(regex::Error::CompiledTooBig(_))
+        ));
+    }
+
+    #[test]
+    fn incremental_search_treats_the_last_word_as_a_prefix() {
+        let mut index = Index::new();
+        let rust = index.add_text("rust ownership");
+        let async_doc = index.add_text("async runtimes");
+        let assert_doc = index.add_text("assertions everywhere");
+        index.add_text("go channels");
+
+        let mut results = index.search_incremental("rust as");
+        results.sort();
+        let mut expected = vec![rust, async_doc, assert_doc];
+        expected.sort();
+        assert_eq!(results, expected);
+
+        // A single partial word is just a prefix search
+        assert_eq!(index.search_incremental("asy"), vec![async_doc]);
+        assert!(index.search_incremental("zz").is_empty());
+        assert!(index.search_incremental("").is_empty());
+    }
+
+    #[test]
+    fn incremental_search_with_trailing_space_is_a_plain_search() {
+        let mut index = Index::new();
+        index.add_text("rust ownership");
+        index.add_text("rustacean crab");
+
+        assert_eq!(
+            index.search_incremental("rust "),
+            index.search_query("rust")
+        );
+        assert_eq!(index.search_incremental("rust ").len(), 1);
+        assert_eq!(index.search_incremental("rust").len(), 2);
+    }
+}
@@ -0,0 +1,233 @@
+//! Compact storage for the documents containing a term.
+//!
+//! Most terms in a notes corpus show up in a handful of documents, where a
+//! `HashSet<DocumentId>` spends more on its table than on the ids. A sorted `Vec`
+//! stores just the ids, answers membership with a binary search and lets
+//! intersections and unions run as a single linear merge.
+
+use crate::ingestion::DocumentId;
+use serde::{Deserialize, Serialize, Serializer};
+use std::cmp::Ordering;
+
+/// Sorted, duplicate-free list of document ids for one term.
+///
+/// Serializes as a plain JSON array, so index files written when postings
+/// were a `HashSet<DocumentId>` still load (the ids get sorted on the way in).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(from = "Vec<DocumentId>")]
+pub struct PostingList(Vec<DocumentId>);
+
+impl PostingList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `id`, returning false if it was already present.
+    pub fn insert(&mut self, id: DocumentId) -> bool {
+        match self.0.binary_search(&id) {
+            Ok(_) => false,
+            Err(at) => {
+                self.0.insert(at, id);
+                true
+            }
+        }
+    }
+
+    /// Removes `id`, returning whether it was present.
+    pub fn remove(&mut self, id: &DocumentId) -> bool {
+        match self.0.binary_search(id) {
+            Ok(at) => {
+                self.0.remove(at);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn contains(&self, id: &DocumentId) -> bool {
+        self.0.binary_search(id).is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Releases spare capacity left behind by removals.
+    pub fn shrink_to_fit(&mut self) {
+        self.0.shrink_to_fit();
+    }
+
+    /// Ids in ascending order.
+    pub fn iter(&self) -> std::slice::Iter<'_, DocumentId> {
+        self.0.iter()
+    }
+
+    /// Ids present in both lists, ascending.
+    pub fn intersection<'a>(
+        &'a self,
+        other: &'a PostingList,
+    ) -> impl Iterator<Item = &'a DocumentId> {
+        Merge::new(&self.0, &other.0).filter_map(|step| match step {
+            MergeStep::Both(id) => Some(id),
+            MergeStep::Left(_) | MergeStep::Right(_) => None,
+        })
+    }
+
+    /// Ids present in either list, ascending and without duplicates.
+    pub fn union<'a>(&'a self, other: &'a PostingList) -> impl Iterator<Item = &'a DocumentId> {
+        Merge::new(&self.0, &other.0).map(|step| match step {
+            MergeStep::Both(id) | MergeStep::Left(id) | MergeStep::Right(id) => id,
+        })
+    }
+}
+
+impl From<Vec<DocumentId>> for PostingList {
+    fn from(mut ids: Vec<DocumentId>) -> Self {
+        ids.sort_unstable();
+        ids.dedup();
+        PostingList(ids)
+    }
+}
+
+impl FromIterator<DocumentId> for PostingList {
+    fn from_iter<I: IntoIterator<Item = DocumentId>>(iter: I) -> Self {
+        PostingList::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<'a> IntoIterator for &'a PostingList {
+    type Item = &'a DocumentId;
+    type IntoIter = std::slice::Iter<'a, DocumentId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Serialize for PostingList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+enum MergeStep<'a> {
+    Left(&'a DocumentId),
+    Right(&'a DocumentId),
+    Both(&'a DocumentId),
+}
+
+// Walks two sorted slices side by side, like the merge step of merge sort.
+struct Merge<'a> {
+    left: &'a [DocumentId],
+    right: &'a [DocumentId],
+}
+
+impl<'a> Merge<'a> {
+    fn new(left: &'a [DocumentId], right: &'a [DocumentId]) -> Self {
+        Merge { left, right }
+    }
+}
+
+impl<'a> Iterator for Merge<'a> {
+    type Item = MergeStep<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = match (self.left.first(), self.right.first()) {
+            (None, None) => return None,
+            (Some(l), None) => MergeStep::Left(l),
+            (None, Some(r)) => MergeStep::Right(r),
+            (Some(l), Some(r)) => match l.cmp(r) {
+                Ordering::Less => MergeStep::Left(l),
+                Ordering::Greater => MergeStep::Right(r),
+                Ordering::Equal => MergeStep::Both(l),
+            },
+        };
+
+        match step {
+            MergeStep::Left(_) => self.left = &self.left[1..],
+            MergeStep::Right(_) => self.right = &self.right[1..],
+            MergeStep::Both(_) => {
+                self.left = &self.left[1..];
+                self.right = &self.right[1..];
+            }
+        }
+
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn sorted(ids: impl IntoIterator<Item = DocumentId>) -> Vec<DocumentId> {
+        let mut ids: Vec<DocumentId> = ids.into_iter().collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn behaves_like_a_hash_set() {
+        let ids: Vec<DocumentId> = (0..50).map(|_| DocumentId::random()).collect();
+        let mut list = PostingList::new();
+        let mut set = HashSet::new();
+
+        // Same inserts (with repeats) and removals applied to both
+        for id in ids.iter().chain(&ids[..10]) {
+            assert_eq!(list.insert(*id), set.insert(*id));
+        }
+        for id in ids.iter().step_by(3) {
+            assert_eq!(list.remove(id), set.remove(id));
+        }
+        assert!(!list.remove(&DocumentId::random()));
+
+        assert_eq!(list.len(), set.len());
+        for id in &ids {
+            assert_eq!(list.contains(id), set.contains(id));
+        }
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), sorted(set));
+    }
+
+    #[test]
+    fn intersection_and_union_match_hash_set() {
+        let ids: Vec<DocumentId> = (0..40).map(|_| DocumentId::random()).collect();
+        let a_ids: HashSet<DocumentId> = ids[..25].iter().copied().collect();
+        let b_ids: HashSet<DocumentId> = ids[15..].iter().copied().collect();
+
+        let a: PostingList = a_ids.iter().copied().collect();
+        let b: PostingList = b_ids.iter().copied().collect();
+
+        assert_eq!(
+            a.intersection(&b).copied().collect::<Vec<_>>(),
+            sorted(a_ids.intersection(&b_ids).copied())
+        );
+        assert_eq!(
+            a.union(&b).copied().collect::<Vec<_>>(),
+            sorted(a_ids.union(&b_ids).copied())
+        );
+
+        let empty = PostingList::new();
+        assert_eq!(a.intersection(&empty).count(), 0);
+        assert_eq!(empty.union(&a).count(), a.len());
+    }
+
+    #[test]
+    fn reads_hash_set_json() {
+        // Old index files stored postings as an unordered array
+        let set: HashSet<DocumentId> = (0..5).map(|_| DocumentId::random()).collect();
+        let json = serde_json::to_string(&set).unwrap();
+
+        let list: PostingList = serde_json::from_str(&json).unwrap();
+        assert_eq!(list.iter().copied().collect::<Vec<_>>(), sorted(set));
+
+        // And writes the same shape back out
+        let round_trip: HashSet<DocumentId> =
+            serde_json::from_str(&serde_json::to_string(&list).unwrap()).unwrap();
+        assert_eq!(round_trip.len(), 5);
+    }
+}
@@ -0,0 +1,120 @@
+//! Position-aware queries built on the per-document token positions.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize_with;
+
+impl Index {
+    /// Documents where `a` and `b` occur within `max_gap` token positions of
+    /// each other, in either order. Adjacent words are a gap of 1, so an
+    /// exact two-word phrase is the ordered case of `max_gap == 1`.
+    ///
+    /// Each side is normalized by the tokenizer and only its first token is
+    /// used. Searching a term near itself needs two separate occurrences.
+    /// Results are ordered like `search_query`.
+    pub fn search_near(&self, a: &str, b: &str, max_gap: usize) -> Vec<DocumentId> {
+        // 1. Normalize both sides exactly like indexed text
+        let (Some(a), Some(b)) = (self.first_token(a), self.first_token(b)) else {
+            return Vec::new();
+        };
+
+        // 2. Only documents containing both terms can qualify
+        let (Some(docs_a), Some(docs_b)) = (self.postings.get(&a), self.postings.get(&b)) else {
+            return Vec::new();
+        };
+
+        let mut results: Vec<DocumentId> = docs_a
+            .intersection(docs_b)
+            .filter(|doc_id| {
+                let Some(positions) = self.positions.get(doc_id) else {
+                    return false;
+                };
+
+                match (positions.get(&a), positions.get(&b)) {
+                    (Some(pos_a), Some(pos_b)) => within_gap(pos_a, pos_b, max_gap),
+                    _ => false,
+                }
+            })
+            .copied()
+            .collect();
+
+        self.order_results(&mut results, &[a, b]);
+        results
+    }
+
+    // Normalizes a single term the way indexed text is, e.g. "Rust!" -> "rust".
+    pub(super) fn first_token(&self, text: &str) -> Option<String> {
+        tokenize_with(text, &self.config.tokenizer)
+            .into_iter()
+            .next()
+    }
+}
+
+// True if some pair of distinct positions is 1..=max_gap apart. Both lists
+// are sorted, so a two-pointer walk finds the closest pairs in O(a + b).
+fn within_gap(pos_a: &[usize], pos_b: &[usize], max_gap: usize) -> bool {
+    let (mut i, mut j) = (0, 0);
+
+    while i < pos_a.len() && j < pos_b.len() {
+        let gap = pos_a[i].abs_diff(pos_b[j]);
+
+        if gap >= 1 && gap <= max_gap {
+            return true;
+        }
+
+        if pos_a[i] < pos_b[j] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+
+    fn index_with(content: &str) -> (Index, DocumentId) {
+        let mut index = Index::new();
+        let doc = test_doc("note.txt", content);
+        let doc_id = doc.id;
+        index.add_document(doc);
+        (index, doc_id)
+    }
+
+    #[test]
+    fn near_matches_adjacent_terms_in_either_order() {
+        let (index, doc_id) = index_with("learning async rust this week");
+
+        assert_eq!(index.search_near("async", "rust", 1), vec![doc_id]);
+        assert_eq!(index.search_near("Rust", "ASYNC", 1), vec![doc_id]);
+    }
+
+    #[test]
+    fn near_matches_within_gap_but_not_beyond() {
+        // rust at 0, async at 4
+        let (index, doc_id) = index_with("rust makes writing fast async code pleasant");
+
+        assert_eq!(index.search_near("rust", "async", 4), vec![doc_id]);
+        assert_eq!(index.search_near("rust", "async", 10), vec![doc_id]);
+        assert!(index.search_near("rust", "async", 3).is_empty());
+        assert!(index.search_near("rust", "async", 0).is_empty());
+    }
+
+    #[test]
+    fn near_needs_both_terms_and_distinct_occurrences() {
+        let (index, _) = index_with("rust is fun");
+
+        assert!(index.search_near("rust", "missing", 5).is_empty());
+        assert!(index.search_near("!!!", "rust", 5).is_empty());
+
+        // A single "rust" is not near itself
+        assert!(index.search_near("rust", "rust", 5).is_empty());
+
+        let (index, doc_id) = index_with("rust and more rust");
+        assert_eq!(index.search_near("rust", "rust", 3), vec![doc_id]);
+    }
+}
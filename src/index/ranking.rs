@@ -0,0 +1,690 @@
+//! Relevance ranking on top of the inverted index.
+
+use super::Index;
+use crate::ingestion::{Document, DocumentId};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::{Duration, SystemTime};
+
+/// Scorer used by `Index::search_ranked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScoringMode {
+    /// Okapi BM25 with the builder's `k1` / `b`. Rewards repeated mentions
+    /// with diminishing returns and penalizes long documents.
+    Bm25,
+    /// Cosine similarity between TF-IDF vectors. Rewards documents that are
+    /// mostly about the query terms.
+    TfIdf,
+}
+
+/// One result of `Index::search_ranked_with_coverage`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedMatch {
+    pub id: DocumentId,
+    pub score: f32,
+    /// Distinct query terms found in the document / distinct query terms,
+    /// between 0 and 1
+    pub coverage: f32,
+}
+
+// Intermediate result of `score_documents`
+#[derive(Default)]
+struct DocumentScores {
+    scores: HashMap<DocumentId, f32>,
+    // Distinct query terms each document matched
+    matched: HashMap<DocumentId, usize>,
+    // Distinct query terms, known to the index or not
+    term_count: f32,
+}
+
+// A `search_top_k` candidate, ordered by rank so the max-heap's top is the
+// worst one kept.
+struct HeapEntry<'a> {
+    hit: (DocumentId, f32),
+    index: &'a Index,
+}
+
+impl Ord for HeapEntry<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.rank_cmp(&self.hit, &other.hit)
+    }
+}
+
+impl PartialOrd for HeapEntry<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry<'_> {}
+
+impl Index {
+    /// Returns every document containing at least one query token, paired
+    /// with its relevance score, best first.
+    ///
+    /// A term can be weighted with a `^` suffix: in `rust^2 async`, "rust"
+    /// counts twice as much as usual.
+    ///
+    /// Equal scores are ordered by the index's `OrderBy` tiebreak.
+    pub fn search_ranked(&self, query: &str) -> Vec<(DocumentId, f32)> {
+        self.search_ranked_with_coverage(query)
+            .into_iter()
+            .map(|hit| (hit.id, hit.score))
+            .collect()
+    }
+
+    /// `search_ranked`, plus the fraction of distinct query terms each
+    /// document contains (`coverage`), for things like confidence badges.
+    pub fn search_ranked_with_coverage(&self, query: &str) -> Vec<RankedMatch> {
+        let scored = self.score_documents(query);
+
+        self.sort_scored(scored.scores.into_iter().collect())
+            .into_iter()
+            .map(|(id, score)| RankedMatch {
+                id,
+                score,
+                coverage: scored.matched[&id] as f32 / scored.term_count,
+            })
+            .collect()
+    }
+
+    /// The single best `search_ranked` result, without sorting the rest.
+    /// Ties are broken the same way, so this is always
+    /// `search_ranked(query).first()`.
+    pub fn search_first(&self, query: &str) -> Option<DocumentId> {
+        self.score_documents(query)
+            .scores
+            .into_iter()
+            .min_by(|a, b| self.rank_cmp(a, b))
+            .map(|(id, _)| id)
+    }
+
+    /// The best `k` `search_ranked` results, best first, without sorting the
+    /// rest. Same scores and tiebreaks, so this is always
+    /// `search_ranked(query)` truncated to `k`.
+    pub fn search_top_k(&self, query: &str, k: usize) -> Vec<(DocumentId, f32)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        // 1. Keep the best k seen so far; the heap's top is the worst of them.
+        //    Sized by the matches, `k` may be anything up to usize::MAX.
+        let scores = self.score_documents(query).scores;
+        let mut heap: BinaryHeap<HeapEntry> = BinaryHeap::with_capacity(k.min(scores.len()));
+
+        for hit in scores {
+            if heap.len() < k {
+                heap.push(HeapEntry { hit, index: self });
+            } else if let Some(mut worst) = heap.peek_mut()
+                && self.rank_cmp(&hit, &worst.hit) == Ordering::Less
+            {
+                worst.hit = hit;
+            }
+        }
+
+        // 2. Ascending in rank order is best first
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.hit)
+            .collect()
+    }
+
+    // Scores every document matching at least one query token, unsorted.
+    fn score_documents(&self, query: &str) -> DocumentScores {
+        let mut scored = DocumentScores::default();
+
+        // 1. Nothing to rank, and N = 0 would poison every IDF below
+        if self.documents.is_empty() {
+            return scored;
+        }
+
+        // 2. Distinct query tokens that actually exist in the index. Coverage
+        //    is measured against all of them, known to the index or not.
+        //    `term^2` weights come off first so the numbers aren't searched.
+        let query = self.parse_boosts(query);
+        let all_tokens: HashSet<String> = self.query_tokens(&query.text).into_iter().collect();
+        scored.term_count = all_tokens.len() as f32;
+        let mut query_tokens: HashSet<String> = all_tokens
+            .into_iter()
+            .filter(|token| self.postings.contains_key(token))
+            .collect();
+
+        if query_tokens.is_empty() {
+            return scored;
+        }
+
+        // 2b. Drop the dynamic stop words, unless that would leave nothing
+        if let Some(max_fraction) = self.config.max_doc_frequency {
+            let doc_count = self.documents.len() as f32;
+            let is_common =
+                |token: &String| self.postings[token].len() as f32 / doc_count > max_fraction;
+
+            if !query_tokens.iter().all(is_common) {
+                query_tokens.retain(|token| !is_common(token));
+            }
+        }
+
+        // 3. Accumulate per-document scores term by term, counting how many
+        //    distinct terms each document matched along the way
+        let DocumentScores {
+            scores, matched, ..
+        } = &mut scored;
+        let doc_count = self.documents.len() as f32;
+        let avg_len = self.average_doc_length();
+
+        for token in &query_tokens {
+            let doc_ids = &self.postings[token];
+            let doc_freq = doc_ids.len() as f32;
+
+            for doc_id in doc_ids {
+                let tf = self.term_frequency(*doc_id, token) as f32;
+
+                let contribution = match self.config.scoring {
+                    ScoringMode::Bm25 => {
+                        let idf = bm25_idf(doc_count, doc_freq);
+                        let doc_len = self.doc_lengths.get(doc_id).copied().unwrap_or(0) as f32;
+                        idf * self.bm25_tf(tf, doc_len, avg_len) * query.boost(token)
+                    }
+                    // Query weights are idf (each distinct term once), times
+                    // any boost
+                    ScoringMode::TfIdf => {
+                        let idf = tf_idf_idf(doc_count, doc_freq);
+                        tf * idf * idf * query.boost(token)
+                    }
+                };
+
+                *scores.entry(*doc_id).or_default() += contribution;
+                *matched.entry(*doc_id).or_default() += 1;
+            }
+        }
+
+        // 4. TF-IDF needs both vectors normalized to turn the dot product
+        //    into a cosine
+        if self.config.scoring == ScoringMode::TfIdf {
+            let query_norm = query_tokens
+                .iter()
+                .map(|token| {
+                    let idf = tf_idf_idf(doc_count, self.postings[token].len() as f32);
+                    (idf * query.boost(token)).powi(2)
+                })
+                .sum::<f32>()
+                .sqrt();
+
+            for (doc_id, score) in scores.iter_mut() {
+                let doc_norm = self.tf_idf_norm(*doc_id, doc_count);
+                if doc_norm > 0.0 && query_norm > 0.0 {
+                    *score /= doc_norm * query_norm;
+                }
+            }
+        }
+
+        // 5. Optionally favour recently modified documents
+        if let Some(half_life) = self.config.recency_half_life {
+            let now = SystemTime::now();
+            for (doc_id, score) in scores.iter_mut() {
+                *score *= self.recency_factor(*doc_id, half_life, now);
+            }
+        }
+
+        scored
+    }
+
+    /// `search_ranked` as an id -> score map, for callers that combine
+    /// scores from several indexes (e.g. summing per shard) before sorting.
+    pub fn score_map(&self, query: &str) -> HashMap<DocumentId, f32> {
+        self.search_ranked(query).into_iter().collect()
+    }
+
+    /// `search_ranked` with each id resolved to its stored `Document`, so
+    /// callers don't need a second lookup pass. Same order and scores.
+    pub fn search_ranked_documents(&self, query: &str) -> Vec<(&Document, f32)> {
+        self.search_ranked(query)
+            .into_iter()
+            .filter_map(|(id, score)| Some((self.documents.get(&id)?, score)))
+            .collect()
+    }
+
+    /// `search_ranked` without the weak matches: anything scoring below
+    /// `min_score` is dropped. A threshold of 0 keeps every result.
+    pub fn search_ranked_above(&self, query: &str, min_score: f32) -> Vec<(DocumentId, f32)> {
+        let mut results = self.search_ranked(query);
+        results.retain(|(_, score)| *score >= min_score);
+        results
+    }
+
+    // Best score first, ties broken by the configured `OrderBy` so output
+    // is reproducible.
+    pub(super) fn sort_scored(&self, mut scored: Vec<(DocumentId, f32)>) -> Vec<(DocumentId, f32)> {
+        scored.sort_by(|a, b| self.rank_cmp(a, b));
+
+        scored
+    }
+
+    // `Less` when `a` ranks before `b`
+    fn rank_cmp(&self, a: &(DocumentId, f32), b: &(DocumentId, f32)) -> Ordering {
+        b.1.total_cmp(&a.1)
+            .then_with(|| self.compare_tiebreak(&a.0, &b.0))
+    }
+
+    // Exponential decay by age: 1.0 for a document modified just now, 0.5
+    // one half-life ago, and so on. Unknown ages and future timestamps get
+    // the neutral 1.0.
+    fn recency_factor(&self, doc_id: DocumentId, half_life: Duration, now: SystemTime) -> f32 {
+        let Some(modified) = self.documents.get(&doc_id).and_then(|doc| doc.modified) else {
+            return 1.0;
+        };
+
+        let half_life = half_life.as_secs_f64();
+        if half_life <= 0.0 {
+            return 1.0;
+        }
+
+        let age = now.duration_since(modified).unwrap_or_default();
+        0.5_f64.powf(age.as_secs_f64() / half_life) as f32
+    }
+
+    fn term_frequency(&self, doc_id: DocumentId, token: &str) -> usize {
+        self.term_freqs
+            .get(&doc_id)
+            .and_then(|freqs| freqs.get(token))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn average_doc_length(&self) -> f32 {
+        if self.doc_lengths.is_empty() {
+            return 0.0;
+        }
+
+        self.doc_lengths.values().sum::<usize>() as f32 / self.doc_lengths.len() as f32
+    }
+
+    // BM25 term-frequency component. A corpus of only empty documents has an
+    // average length of 0, in which case length normalization is skipped.
+    fn bm25_tf(&self, tf: f32, doc_len: f32, avg_len: f32) -> f32 {
+        let k1 = self.config.k1;
+        let b = self.config.b;
+
+        let length_ratio = if avg_len > 0.0 {
+            doc_len / avg_len
+        } else {
+            1.0
+        };
+        let norm = k1 * (1.0 - b + b * length_ratio);
+
+        if tf + norm == 0.0 {
+            return 0.0;
+        }
+
+        tf * (k1 + 1.0) / (tf + norm)
+    }
+
+    // Length of the document's TF-IDF vector over all of its terms.
+    fn tf_idf_norm(&self, doc_id: DocumentId, doc_count: f32) -> f32 {
+        let Some(freqs) = self.term_freqs.get(&doc_id) else {
+            return 0.0;
+        };
+
+        freqs
+            .iter()
+            .map(|(token, tf)| {
+                let doc_freq = self.postings.get(token).map_or(1, |ids| ids.len()) as f32;
+                (*tf as f32 * tf_idf_idf(doc_count, doc_freq)).powi(2)
+            })
+            .sum::<f32>()
+            .sqrt()
+    }
+}
+
+// Probabilistic IDF with +1 inside the log so it never goes negative for
+// terms in more than half the corpus.
+fn bm25_idf(doc_count: f32, doc_freq: f32) -> f32 {
+    (1.0 + (doc_count - doc_freq + 0.5) / (doc_freq + 0.5)).ln()
+}
+
+// Smoothed IDF, always >= 1 so ubiquitous terms still count a little.
+fn tf_idf_idf(doc_count: f32, doc_freq: f32) -> f32 {
+    (doc_count / doc_freq).ln() + 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+
+    // "repeat" says rust a lot among other words, "focused" says it once and
+    // nothing else. BM25 should prefer the repetition, cosine TF-IDF the focus.
+    fn crafted_corpus(builder: crate::index::IndexBuilder) -> (Index, DocumentId, DocumentId) {
+        let mut index = builder.build();
+
+        let repeat = test_doc(
+            "repeat.txt",
+            "rust rust rust rust rust rust rust rust rust rust alpha beta gamma delta",
+        );
+        let focused = test_doc("focused.txt", "rust");
+        let unrelated = test_doc("unrelated.txt", "gardening in spring");
+
+        let repeat_id = repeat.id;
+        let focused_id = focused.id;
+
+        index.add_document(repeat);
+        index.add_document(focused);
+        index.add_document(unrelated);
+
+        (index, repeat_id, focused_id)
+    }
+
+    #[test]
+    fn bm25_is_the_default_and_rewards_term_frequency() {
+        let (index, repeat_id, focused_id) = crafted_corpus(Index::builder());
+
+        let results = index.search_ranked("rust");
+        let ids: Vec<DocumentId> = results.iter().map(|(id, _)| *id).collect();
+
+        assert_eq!(ids, vec![repeat_id, focused_id]);
+        assert!(results.iter().all(|(_, score)| *score > 0.0));
+    }
+
+    #[test]
+    fn tf_idf_prefers_focused_documents() {
+        let (index, repeat_id, focused_id) =
+            crafted_corpus(Index::builder().scoring(ScoringMode::TfIdf));
+
+        let results = index.search_ranked("rust");
+        let ids: Vec<DocumentId> = results.iter().map(|(id, _)| *id).collect();
+
+        assert_eq!(ids, vec![focused_id, repeat_id]);
+
+        // A document that is only the query term is a perfect cosine match
+        assert!((results[0].1 - 1.0).abs() < 1e-5);
+        assert!(results[1].1 < 1.0);
+    }
+
+    #[test]
+    fn bm25_parameters_come_from_the_builder() {
+        // k1 = 0 turns off term frequency entirely, leaving only IDF, so both
+        // docs tie and fall back to path order
+        let (index, repeat_id, focused_id) = crafted_corpus(Index::builder().bm25(0.0, 0.75));
+
+        let results = index.search_ranked("rust");
+
+        assert_eq!(results[0].0, focused_id);
+        assert_eq!(results[1].0, repeat_id);
+        assert_eq!(results[0].1, results[1].1);
+    }
+
+    #[test]
+    fn search_ranked_documents_pairs_documents_with_scores() {
+        let (index, _, _) = crafted_corpus(Index::builder());
+
+        let ranked = index.search_ranked("rust");
+        let documents = index.search_ranked_documents("rust");
+
+        assert_eq!(documents.len(), ranked.len());
+        for ((doc, score), (id, expected)) in documents.iter().zip(&ranked) {
+            assert_eq!(doc.id, *id);
+            assert_eq!(score, expected);
+        }
+        assert!(index.search_ranked_documents("missing").is_empty());
+    }
+
+    #[test]
+    fn search_ranked_above_drops_weak_matches() {
+        let mut index = Index::new();
+
+        // Both words are rare, but only one document has both
+        let strong = test_doc("strong.txt", "rust async rust async");
+        let weak = test_doc("weak.txt", "rust and some unrelated filler words here");
+        let strong_id = strong.id;
+
+        index.add_document(strong);
+        index.add_document(weak);
+        index.add_document(test_doc("other.txt", "gardening"));
+
+        let all = index.search_ranked("rust async");
+        assert_eq!(all.len(), 2);
+
+        // Threshold between the two scores keeps only the strong match
+        let cutoff = (all[0].1 + all[1].1) / 2.0;
+        let filtered = index.search_ranked_above("rust async", cutoff);
+        assert_eq!(filtered, vec![all[0]]);
+        assert_eq!(filtered[0].0, strong_id);
+
+        // Zero keeps everything, an unreachable threshold keeps nothing
+        assert_eq!(index.search_ranked_above("rust async", 0.0), all);
+        assert!(index.search_ranked_above("rust async", f32::MAX).is_empty());
+    }
+
+    #[test]
+    fn ranking_an_empty_index_returns_nothing() {
+        let builders = [
+            Index::builder(),
+            Index::builder().scoring(ScoringMode::TfIdf),
+            Index::builder().recency_boost(Duration::from_secs(60)),
+        ];
+
+        for builder in builders {
+            let index = builder.build();
+
+            assert!(index.search_ranked("rust").is_empty());
+            assert!(index.search_ranked("").is_empty());
+            assert!(index.search_ranked_with_coverage("rust").is_empty());
+            assert!(index.search_ranked_above("rust", 0.0).is_empty());
+            assert!(index.score_map("rust").is_empty());
+            assert_eq!(index.search_first("rust"), None);
+        }
+    }
+
+    #[test]
+    fn search_first_returns_the_top_ranked_document() {
+        for mode in [ScoringMode::Bm25, ScoringMode::TfIdf] {
+            let (index, repeat_id, focused_id) = crafted_corpus(Index::builder().scoring(mode));
+            let expected = match mode {
+                ScoringMode::Bm25 => repeat_id,
+                ScoringMode::TfIdf => focused_id,
+            };
+
+            assert_eq!(index.search_first("rust"), Some(expected));
+            assert_eq!(
+                index.search_first("rust"),
+                index.search_ranked("rust").first().map(|(id, _)| *id)
+            );
+            assert_eq!(index.search_first("missing"), None);
+        }
+    }
+
+    #[test]
+    fn search_top_k_matches_the_head_of_search_ranked() {
+        for mode in [ScoringMode::Bm25, ScoringMode::TfIdf] {
+            let mut index = Index::builder().scoring(mode).build();
+
+            // Plenty of ties (identical documents) so the tiebreak matters
+            for i in 0..40 {
+                let content = format!("rust {}", "filler ".repeat(i % 7));
+                index.add_document(test_doc(format!("note_{i}.md"), &content));
+            }
+
+            let ranked = index.search_ranked("rust filler");
+            for k in [0, 1, 5, 13, 40, 100, usize::MAX] {
+                let expected: Vec<_> = ranked.iter().copied().take(k).collect();
+                assert_eq!(index.search_top_k("rust filler", k), expected);
+            }
+            assert!(index.search_top_k("missing", 5).is_empty());
+        }
+    }
+
+    #[test]
+    fn ranking_ignores_empty_documents_without_nan() {
+        for mode in [ScoringMode::Bm25, ScoringMode::TfIdf] {
+            let mut index = Index::builder().scoring(mode).build();
+            index.add_document(test_doc("empty.txt", ""));
+            index.add_document(test_doc("blank.txt", "   "));
+
+            // Only empty documents: average length is 0
+            assert!(index.search_ranked("rust").is_empty());
+
+            let hit = test_doc("hit.txt", "rust");
+            let hit_id = hit.id;
+            index.add_document(hit);
+
+            let results = index.search_ranked("rust");
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].0, hit_id);
+            assert!(results[0].1.is_finite());
+        }
+    }
+
+    #[test]
+    fn recency_boost_ranks_newer_of_equal_documents_first() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = SystemTime::now();
+
+        let mut old = test_doc("a_old.txt", "rust notes");
+        old.modified = Some(now - day * 30);
+        let mut new = test_doc("b_new.txt", "rust notes");
+        new.modified = Some(now - day);
+        let undated = test_doc("c_undated.txt", "rust notes");
+        let (old_id, new_id, undated_id) = (old.id, new.id, undated.id);
+
+        let docs = [old, new, undated];
+
+        // Without the boost the tie falls back to path order
+        let mut plain = Index::new();
+        plain.add_documents(docs.clone());
+        let ids: Vec<DocumentId> = plain.search_ranked("rust").iter().map(|r| r.0).collect();
+        assert_eq!(ids, vec![old_id, new_id, undated_id]);
+
+        let mut boosted = Index::builder().recency_boost(day * 7).build();
+        boosted.add_documents(docs);
+        let results = boosted.search_ranked("rust");
+        let ids: Vec<DocumentId> = results.iter().map(|r| r.0).collect();
+
+        // Undated keeps the full score, the month-old note decays the most
+        assert_eq!(ids, vec![undated_id, new_id, old_id]);
+        assert!(results[2].1 < results[1].1 / 10.0);
+    }
+
+    #[test]
+    fn coverage_is_the_fraction_of_query_terms_matched() {
+        let mut index = Index::new();
+        let both = test_doc("both.txt", "rust async");
+        let one = test_doc("one.txt", "rust ownership");
+        let (both_id, one_id) = (both.id, one.id);
+        index.add_document(both);
+        index.add_document(one);
+
+        let results = index.search_ranked_with_coverage("rust async");
+        assert_eq!(results.len(), 2);
+        assert_eq!((results[0].id, results[0].coverage), (both_id, 1.0));
+        assert_eq!((results[1].id, results[1].coverage), (one_id, 0.5));
+
+        // Scores agree with plain search_ranked
+        let ranked = index.search_ranked("rust async");
+        assert_eq!(ranked[0], (results[0].id, results[0].score));
+
+        // Terms missing from the whole index still count as unmatched
+        let results = index.search_ranked_with_coverage("ownership missing");
+        assert_eq!(results[0].coverage, 0.5);
+    }
+
+    #[test]
+    fn score_map_matches_search_ranked() {
+        let (index, _, _) = crafted_corpus(Index::builder());
+
+        let ranked = index.search_ranked("rust gardening");
+        let map = index.score_map("rust gardening");
+
+        assert_eq!(map.len(), ranked.len());
+        for (id, score) in ranked {
+            assert_eq!(map[&id], score);
+        }
+        assert!(index.score_map("missing").is_empty());
+    }
+
+    // Twenty notes all mentioning "note", one of them also "quasar"
+    fn common_term_corpus(builder: crate::index::IndexBuilder) -> (Index, DocumentId) {
+        let mut index = builder.build();
+        for i in 0..19 {
+            index.add_document(test_doc(format!("note{i}.md"), "note about things"));
+        }
+
+        let rare = test_doc("rare.md", "note about quasar things");
+        let rare_id = rare.id;
+        index.add_document(rare);
+
+        (index, rare_id)
+    }
+
+    #[test]
+    fn idf_makes_near_ubiquitous_terms_count_for_little() {
+        let (index, rare_id) = common_term_corpus(Index::builder());
+        let common_score = index.search_ranked("note")[0].1;
+        let rare_score = index.search_ranked("quasar")[0].1;
+
+        assert!(common_score > 0.0);
+        assert!(
+            common_score < rare_score * 0.05,
+            "{common_score} vs {rare_score}"
+        );
+
+        // The rare term dominates a mixed query
+        let ranked = index.search_ranked("note quasar");
+        assert_eq!(ranked[0].0, rare_id);
+        assert!(ranked[1].1 < ranked[0].1 * 0.05);
+    }
+
+    #[test]
+    fn max_doc_frequency_skips_common_terms_when_ranking() {
+        let (index, rare_id) = common_term_corpus(Index::builder().max_doc_frequency(0.5));
+        let (plain, _) = common_term_corpus(Index::builder());
+
+        // "note" is in every document, so only "quasar" is scored
+        let ranked = index.search_ranked("note quasar");
+        assert_eq!(ranked.len(), 1);
+        assert_eq!(ranked[0].0, rare_id);
+        assert_eq!(ranked[0].1, plain.search_ranked("quasar")[0].1);
+
+        // Coverage still counts the skipped term
+        assert_eq!(
+            index.search_ranked_with_coverage("note quasar")[0].coverage,
+            0.5
+        );
+
+        // A query made only of common terms still ranks
+        assert_eq!(index.search_ranked("note").len(), 20);
+        assert_eq!(index.search_query("note quasar").len(), 20);
+    }
+
+    #[test]
+    fn boosted_terms_reorder_results() {
+        for mode in [ScoringMode::Bm25, ScoringMode::TfIdf] {
+            let mut index = Index::builder().scoring(mode).build();
+            let about_rust = test_doc("rust.md", "rust borrowing notes");
+            let about_async = test_doc("async.md", "async runtime notes");
+            let (rust_id, async_id) = (about_rust.id, about_async.id);
+            index.add_documents([about_rust, about_async]);
+
+            // Symmetric corpus, so the tiebreak (path) decides unboosted
+            let plain = index.search_ranked("rust async");
+            assert_eq!(plain[0].1, plain[1].1);
+
+            let boosted = index.search_ranked("rust async^3");
+            assert_eq!(boosted[0].0, async_id);
+            assert!(boosted[0].1 > boosted[1].1);
+
+            let boosted = index.search_ranked("rust^3 async");
+            assert_eq!(boosted[0].0, rust_id);
+            assert_eq!(index.search_first("rust^3 async"), Some(rust_id));
+
+            // The boost itself is never searched for
+            assert!(index.search_ranked("3^2").is_empty());
+        }
+    }
+}
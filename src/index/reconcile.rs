@@ -0,0 +1,237 @@
+//! Catching up with changes made while nobody was watching.
+
+use super::Index;
+use crate::ingestion::{
+    DEFAULT_EXTENSIONS, Document, IgnoreRules, IngestError, has_allowed_extension, normalize_path,
+    read_content,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What `Index::reconcile` changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReconcileReport {
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+}
+
+impl Index {
+    /// Brings the index in line with the `.md`/`.txt` files directly inside
+    /// `dir`, e.g. on startup after edits happened while the watcher was down.
+    ///
+    /// - files that aren't indexed yet are added
+    /// - files modified after their indexed `modified` time (or with no
+    ///   recorded time) are re-read and upserted, keeping their id
+    /// - indexed documents under `dir` whose file is gone are removed
+    ///
+    /// Files indexed as chunks (`LoadOptions::chunk`) are only removed when
+    /// they vanish, never added or refreshed: the chunking strategy isn't
+    /// recorded, so they can't be split the same way again here.
+    ///
+    /// Unchanged files aren't read at all, and files excluded by `dir`'s
+    /// ignore files (see `IgnoreRules`) aren't added, same as `load_documents`.
+    pub fn reconcile(&mut self, dir: &Path) -> Result<ReconcileReport, IngestError> {
+        if !dir.is_dir() {
+            return Err(IngestError::NotDirectory);
+        }
+
+        let mut report = ReconcileReport::default();
+        let ignore = IgnoreRules::load(dir)?;
+
+        // Chunks live under `<path>#<offset>`, so look them up by parent
+        let chunked: HashSet<PathBuf> = self
+            .documents
+            .values()
+            .filter_map(|doc| doc.chunk.as_ref())
+            .map(|info| normalize_path(&info.parent_path))
+            .collect();
+
+        // 1. Add or refresh whatever is on disk
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if !entry.file_type()?.is_file()
+                || !has_allowed_extension(&path, DEFAULT_EXTENSIONS)
+                || ignore.is_ignored(&path)
+                || chunked.contains(&normalize_path(&path))
+            {
+                continue;
+            }
+
+            let on_disk = entry.metadata().ok().and_then(|m| m.modified().ok());
+            let existing = self.id_for_path(&path);
+
+            let is_stale = match existing.and_then(|id| self.documents.get(&id)) {
+                None => true,
+                Some(doc) => match (doc.modified, on_disk) {
+                    (Some(indexed), Some(current)) => current > indexed,
+                    _ => true,
+                },
+            };
+
+            if !is_stale {
+                continue;
+            }
+
+            let content = read_content(&path)?;
+            let mut doc = Document::file(path, content, on_disk);
+            if let Some(id) = existing {
+                doc.id = id;
+            }
+            self.upsert_document(doc);
+
+            if existing.is_some() {
+                report.updated += 1;
+            } else {
+                report.added += 1;
+            }
+        }
+
+        // 2. Drop documents whose file disappeared. Chunks live or die with
+        //    their parent file, and synthetic paths never start with `dir`.
+        //    Stored paths are normalized, so `./notes` has to be as well.
+        let dir = normalize_path(dir);
+        let vanished: Vec<PathBuf> = self
+            .documents
+            .values()
+            .filter(|doc| {
+                let file: &PathBuf = doc
+                    .chunk
+                    .as_ref()
+                    .map_or(&doc.path, |info| &info.parent_path);
+
+                normalize_path(file).starts_with(&dir) && !file.exists()
+            })
+            .map(|doc| doc.path.clone())
+            .collect();
+
+        report.removed = self.remove_documents_by_paths(&vanished);
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc_modified;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    #[test]
+    fn reconcile_adds_updates_and_removes() {
+        let dir = std::env::temp_dir().join(format!("reconcile_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let changed = dir.join("changed.md");
+        let fresh = dir.join("fresh.txt");
+        let untouched = dir.join("untouched.txt");
+        let deleted = dir.join("deleted.txt");
+
+        fs::write(&changed, "new content").unwrap();
+        fs::write(&fresh, "brand new note").unwrap();
+        fs::write(&untouched, "same as before").unwrap();
+
+        let untouched_modified = fs::metadata(&untouched).unwrap().modified().unwrap();
+
+        // Index as it looked before the process went down
+        let mut index = Index::new();
+        let stale = test_doc_modified(
+            &changed,
+            "old content",
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1)),
+        );
+        let stale_id = stale.id;
+        index.add_document(stale);
+        index.add_document(test_doc_modified(
+            &untouched,
+            "same as before",
+            Some(untouched_modified),
+        ));
+        index.add_document(test_doc_modified(&deleted, "gone now", None));
+        let text_id = index.add_text("not a file, must survive");
+
+        let report = index.reconcile(&dir).unwrap();
+
+        assert_eq!(
+            report,
+            ReconcileReport {
+                added: 1,
+                updated: 1,
+                removed: 1,
+            }
+        );
+
+        // Changed file was re-read and kept its id
+        assert_eq!(index.search_query("content"), vec![stale_id]);
+        assert!(index.search_query("old").is_empty());
+
+        assert_eq!(index.search_query("brand").len(), 1);
+        assert!(index.search_query("gone").is_empty());
+        assert!(index.id_for_path(&deleted).is_none());
+        assert_eq!(index.search_query("survive"), vec![text_id]);
+
+        // Second pass has nothing left to do
+        assert_eq!(index.reconcile(&dir).unwrap(), ReconcileReport::default());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reconcile_removes_vanished_files_under_a_dot_relative_dir() {
+        // Relative to the crate root, where tests run
+        let dir = Path::new(".")
+            .join("target")
+            .join(format!("reconcile_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "kept note").unwrap();
+        fs::write(dir.join("b.md"), "deleted note").unwrap();
+
+        let mut index = Index::new();
+        index.add_documents(crate::ingestion::load_documents(&dir).unwrap());
+        assert_eq!(index.document_count(), 2);
+
+        fs::remove_file(dir.join("b.md")).unwrap();
+        let report = index.reconcile(&dir).unwrap();
+
+        assert_eq!(report.removed, 1);
+        assert_eq!(index.document_count(), 1);
+        assert!(index.search_query("deleted").is_empty());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reconcile_leaves_chunked_files_alone() {
+        use crate::ingestion::{ChunkStrategy, LoadOptions, load_documents_with};
+
+        let dir = std::env::temp_dir().join(format!("reconcile_chunks_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("long.md"), "alpha first part\n\nsecond part").unwrap();
+        fs::write(dir.join("gone.md"), "beta\n\nmore beta").unwrap();
+
+        let options = LoadOptions {
+            chunk: Some(ChunkStrategy::Paragraph),
+            ..LoadOptions::default()
+        };
+        let mut index = Index::new();
+        index.add_documents(load_documents_with(&dir, &options).unwrap());
+        assert_eq!(index.document_count(), 4);
+
+        // No whole-file duplicate next to the chunks
+        assert_eq!(index.reconcile(&dir).unwrap(), ReconcileReport::default());
+        assert_eq!(index.document_count(), 4);
+        assert_eq!(index.search_query("alpha").len(), 1);
+
+        // Chunks of a deleted file still go
+        fs::remove_file(dir.join("gone.md")).unwrap();
+        assert_eq!(index.reconcile(&dir).unwrap().removed, 2);
+        assert_eq!(index.document_count(), 2);
+        assert_eq!(index.verify(), Ok(()));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,260 @@
+//! Query-centred excerpts of document content with matches highlighted, and
+//! the raw match ranges for clients that highlight themselves.
+//!
+//! All slicing goes through byte offsets produced by `char_indices` or
+//! `tokenize_spans`, never arithmetic on byte lengths, so accented or other
+//! multi-byte text can't be cut mid-character.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use crate::tokenizer::tokenize_spans;
+use std::collections::HashSet;
+use std::ops::Range;
+
+// Markers wrapped around every matched term in a snippet
+const HIGHLIGHT_START: &str = "**";
+const HIGHLIGHT_END: &str = "**";
+
+impl Index {
+    /// An excerpt of at most `max_chars` characters around the first query
+    /// match in a document, with each matched term wrapped in `**`.
+    ///
+    /// Starts at the beginning of the document when nothing matches. A `…`
+    /// marks text cut off on either side. `None` for unknown ids.
+    ///
+    /// If the index doesn't store full content the file is re-read from
+    /// disk.
+    pub fn snippet(&self, id: DocumentId, query: &str, max_chars: usize) -> Option<String> {
+        let content = self.full_content(id)?;
+        let content = content.as_ref();
+
+        // 1. Every occurrence of a query term, as byte ranges into content
+        let matches = self.term_ranges(content, query);
+
+        // 2. Pick a char-aligned window around the first match
+        let focus = matches.first().map_or(0, |range| range.start);
+        let window = char_window(content, focus, max_chars);
+
+        // 3. Copy the window, highlighting matches that fit inside it
+        let mut snippet = String::new();
+        if window.start > 0 {
+            snippet.push('…');
+        }
+
+        let mut copied = window.start;
+        for range in matches
+            .iter()
+            .filter(|range| range.start >= window.start && range.end <= window.end)
+        {
+            snippet.push_str(&content[copied..range.start]);
+            snippet.push_str(HIGHLIGHT_START);
+            snippet.push_str(&content[range.clone()]);
+            snippet.push_str(HIGHLIGHT_END);
+            copied = range.end;
+        }
+        snippet.push_str(&content[copied..window.end]);
+
+        if window.end < content.len() {
+            snippet.push('…');
+        }
+
+        Some(snippet)
+    }
+
+    /// Byte ranges of every query term occurrence in a document's full
+    /// content, sorted by start, so a client can do its own highlighting.
+    ///
+    /// Ranges fall on char boundaries, so `&content[range]` is always safe.
+    /// Empty when nothing matches or the id is unknown. Like `snippet`, the
+    /// file is re-read when the index doesn't store full content.
+    pub fn match_ranges(&self, id: DocumentId, query: &str) -> Vec<Range<usize>> {
+        match self.full_content(id) {
+            Some(content) => self.term_ranges(&content, query),
+            None => Vec::new(),
+        }
+    }
+
+    // Where the query's tokens occur in `content`, in order.
+    fn term_ranges(&self, content: &str, query: &str) -> Vec<Range<usize>> {
+        let query_tokens: HashSet<String> = self.query_tokens(query).into_iter().collect();
+
+        tokenize_spans(content, &self.config.tokenizer)
+            .into_iter()
+            .filter(|(token, _)| query_tokens.contains(token))
+            .map(|(_, range)| range)
+            .collect()
+    }
+}
+
+// Byte range of up to `max_chars` characters of `content`, starting a few
+// characters before the byte offset `focus` so the match has some lead-in.
+// Both ends are char boundaries.
+fn char_window(content: &str, focus: usize, max_chars: usize) -> Range<usize> {
+    // Byte offset of every char start, plus the end of the string
+    let boundaries: Vec<usize> = content
+        .char_indices()
+        .map(|(at, _)| at)
+        .chain(std::iter::once(content.len()))
+        .collect();
+    let char_count = boundaries.len() - 1;
+
+    // Char index of the focus, then back off by a third of the window
+    let focus_char = boundaries.partition_point(|at| *at < focus);
+    let start_char = focus_char
+        .saturating_sub(max_chars / 3)
+        .min(char_count.saturating_sub(max_chars));
+    let end_char = (start_char + max_chars).min(char_count);
+
+    boundaries[start_char]..boundaries[end_char]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::ContentStorage;
+    use uuid::Uuid;
+
+    #[test]
+    fn snippet_centres_on_the_first_match() {
+        let mut index = Index::new();
+        let id = index.add_text("one two three four five six rust seven eight nine ten");
+
+        assert_eq!(
+            index.snippet(id, "rust", 20).unwrap(),
+            "…e six **rust** seven eig…"
+        );
+
+        // No match: the start of the document
+        assert_eq!(index.snippet(id, "missing", 7).unwrap(), "one two…");
+        assert_eq!(index.snippet(DocumentId::random(), "rust", 10), None);
+    }
+
+    #[test]
+    fn snippet_highlights_every_match_in_the_window() {
+        let mut index = Index::new();
+        let id = index.add_text("Rust and more rust");
+
+        assert_eq!(
+            index.snippet(id, "rust", 100).unwrap(),
+            "**Rust** and more **rust**"
+        );
+    }
+
+    #[test]
+    fn snippet_handles_matches_next_to_multibyte_chars() {
+        let mut index = Index::new();
+
+        // Accents, emoji and CJK directly touching the matched term
+        let id = index.add_text("éérustéé 🦀rust🦀 日本rust語");
+
+        assert_eq!(
+            index.snippet(id, "rust", 100).unwrap(),
+            "éé**rust**éé 🦀**rust**🦀 日本**rust**語"
+        );
+
+        // Every window size cuts somewhere inside multi-byte text; none may
+        // panic, and the window never exceeds the requested length
+        for max_chars in 0..30 {
+            let snippet = index.snippet(id, "rust", max_chars).unwrap();
+            let visible = snippet.replace("**", "").replace('…', "");
+            assert!(
+                visible.chars().count() <= max_chars,
+                "{max_chars}: {snippet}"
+            );
+        }
+    }
+
+    #[test]
+    fn char_window_stays_on_char_boundaries() {
+        let text = "ñañañ";
+
+        for focus in 0..=text.len() {
+            for max_chars in 0..=6 {
+                let window = char_window(text, focus, max_chars);
+                assert!(text.get(window.clone()).is_some(), "{focus} {max_chars}");
+            }
+        }
+    }
+
+    #[test]
+    fn snippet_rereads_the_file_when_content_is_not_stored() {
+        let dir = std::env::temp_dir().join(format!("snippet_storage_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        std::fs::write(&path, "notes about rust ownership").unwrap();
+
+        let doc = crate::ingestion::load_document(&path).unwrap();
+        let id = doc.id;
+
+        // Nothing kept in memory, search still works off the postings
+        let mut index = Index::with_content_storage(ContentStorage::None);
+        index.add_document(doc.clone());
+        assert_eq!(index.search_query("ownership"), vec![id]);
+        assert_eq!(index.content_len(id), Some(0));
+        assert_eq!(
+            index.snippet(id, "rust", 100).unwrap(),
+            "notes about **rust** ownership"
+        );
+
+        // A preview keeps just the start
+        let mut index = Index::with_content_storage(ContentStorage::Preview(5));
+        index.add_document(doc);
+        assert_eq!(index.preview(id, 100).unwrap(), "notes");
+        assert_eq!(
+            index.snippet(id, "ownership", 100).unwrap(),
+            "notes about rust **ownership**"
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn match_ranges_cover_every_occurrence() {
+        let mut index = Index::new();
+        let content = "Rust, then rust again; and RUST.";
+        let id = index.add_text(content);
+
+        let ranges = index.match_ranges(id, "rust");
+        assert_eq!(ranges, vec![0..4, 11..15, 27..31]);
+        for range in ranges {
+            assert_eq!(content[range].to_lowercase(), "rust");
+        }
+
+        assert!(index.match_ranges(id, "missing").is_empty());
+        assert!(index.match_ranges(DocumentId::random(), "rust").is_empty());
+    }
+
+    #[test]
+    fn match_ranges_cover_content_that_isnt_stored() {
+        let dir = std::env::temp_dir().join(format!("match_ranges_{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        std::fs::write(&path, "notes about rust ownership").unwrap();
+        let doc = crate::ingestion::load_document(&path).unwrap();
+        let id = doc.id;
+
+        for storage in [ContentStorage::None, ContentStorage::Preview(5)] {
+            let mut index = Index::with_content_storage(storage);
+            index.add_document(doc.clone());
+
+            assert_eq!(
+                index.match_ranges(id, "rust ownership"),
+                vec![12..16, 17..26]
+            );
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn match_ranges_next_to_multi_byte_chars() {
+        let mut index = Index::new();
+        let content = "日本rust→café 🎉rust🎉";
+        let id = index.add_text(content);
+
+        let ranges = index.match_ranges(id, "rust caf");
+        let matched: Vec<&str> = ranges.iter().map(|range| &content[range.clone()]).collect();
+        assert_eq!(matched, vec!["rust", "caf", "rust"]);
+        assert!(ranges.windows(2).all(|pair| pair[0].start < pair[1].start));
+    }
+}
@@ -0,0 +1,146 @@
+//! Ranked results arranged like the folders they live in.
+
+use super::Index;
+use crate::ingestion::DocumentId;
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+/// One folder of `Index::search_tree` results.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultTree {
+    /// Last component of `path`, empty for the root
+    pub name: String,
+    pub path: PathBuf,
+    /// Best score of any document at or below this folder
+    pub score: f32,
+    /// Subfolders containing results, best score first
+    pub folders: Vec<ResultTree>,
+    /// Results directly in this folder, best first
+    pub documents: Vec<(DocumentId, f32)>,
+}
+
+impl ResultTree {
+    fn new(name: String, path: PathBuf) -> Self {
+        ResultTree {
+            name,
+            path,
+            score: 0.0,
+            folders: Vec::new(),
+            documents: Vec::new(),
+        }
+    }
+
+    /// Returns the subfolder called `name`, creating it if needed.
+    fn folder_mut(&mut self, name: &str) -> &mut ResultTree {
+        let position = match self.folders.iter().position(|folder| folder.name == name) {
+            Some(position) => position,
+            None => {
+                let folder = ResultTree::new(name.to_string(), self.path.join(name));
+                self.folders.push(folder);
+                self.folders.len() - 1
+            }
+        };
+
+        &mut self.folders[position]
+    }
+
+    fn sort_folders(&mut self) {
+        self.folders.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        for folder in &mut self.folders {
+            folder.sort_folders();
+        }
+    }
+}
+
+impl Index {
+    /// `search_ranked` results nested by folder, mirroring the filesystem
+    /// (e.g. for a collapsible results tree).
+    ///
+    /// The root is the empty path and only folders leading to a result are
+    /// included. A folder's score is the best score beneath it, so folders
+    /// rank the same way their top hit does in the flat list.
+    pub fn search_tree(&self, query: &str) -> ResultTree {
+        let mut root = ResultTree::new(String::new(), PathBuf::new());
+
+        // 1. search_ranked is already best first, so documents land in each
+        //    folder in order and only the folders need sorting afterwards
+        for (doc_id, score) in self.search_ranked(query) {
+            let Some(doc) = self.documents.get(&doc_id) else {
+                continue;
+            };
+
+            // 2. Walk down (creating folders as needed), raising scores on the way
+            let mut folder = &mut root;
+            folder.score = folder.score.max(score);
+
+            for component in doc.path.parent().unwrap_or(Path::new("")).components() {
+                folder = folder.folder_mut(&component.as_os_str().to_string_lossy());
+                folder.score = folder.score.max(score);
+            }
+
+            folder.documents.push((doc_id, score));
+        }
+
+        // 3. Best folders first at every level
+        root.sort_folders();
+
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingestion::test_doc;
+
+    #[test]
+    fn results_are_nested_by_folder_with_best_scores() {
+        let mut index = Index::new();
+
+        let top = test_doc("notes/top.md", "rust");
+        let deep = test_doc("notes/rust/deep.md", "rust rust rust");
+        let other = test_doc("notes/rust/other.md", "rust and a lot of other words");
+        let go = test_doc("notes/go/go.md", "go with a little rust");
+        let unrelated = test_doc("notes/go/unrelated.md", "goroutines");
+        let (top_id, deep_id, other_id, go_id) = (top.id, deep.id, other.id, go.id);
+        index.add_documents([top, deep, other, go, unrelated]);
+
+        let scores: std::collections::HashMap<_, _> =
+            index.search_ranked("rust").into_iter().collect();
+        let tree = index.search_tree("rust");
+
+        // Root → notes → {rust, go}
+        assert_eq!(tree.path, PathBuf::new());
+        assert_eq!(tree.folders.len(), 1);
+        let notes = &tree.folders[0];
+        assert_eq!(notes.name, "notes");
+        assert_eq!(notes.documents, vec![(top_id, scores[&top_id])]);
+
+        let names: Vec<&str> = notes.folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["rust", "go"]);
+
+        let rust = &notes.folders[0];
+        assert_eq!(rust.path, Path::new("notes/rust"));
+        assert_eq!(
+            rust.documents,
+            vec![(deep_id, scores[&deep_id]), (other_id, scores[&other_id])]
+        );
+        assert!(rust.folders.is_empty());
+        assert_eq!(notes.folders[1].documents, vec![(go_id, scores[&go_id])]);
+
+        // Every folder carries the best score beneath it
+        let best = scores.values().copied().fold(0.0, f32::max);
+        assert_eq!(tree.score, best);
+        assert_eq!(notes.score, best);
+        assert_eq!(rust.score, scores[&deep_id]);
+        assert_eq!(notes.folders[1].score, scores[&go_id]);
+
+        assert!(index.search_tree("missing").folders.is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! Consistency checks over the index's internal maps.
+
+use super::{Index, IndexError};
+use crate::ingestion::DocumentId;
+
+impl Index {
+    /// Checks that the internal maps agree with each other: every posting
+    /// points at a stored document whose token set lists the term, every
+    /// token set is backed by postings, and every per-document map and path
+    /// entry belongs to a stored document.
+    ///
+    /// Returns one human-readable line per problem, sorted. Meant for tests
+    /// and debugging after lots of add/remove churn.
+    pub fn verify(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        // 1. Postings -> documents / doc_tokens
+        for (term, ids) in &self.postings {
+            if ids.is_empty() {
+                problems.push(format!("term {:?} has an empty posting list", term));
+            }
+
+            for id in ids {
+                if !self.documents.contains_key(id) {
+                    problems.push(format!("term {:?} lists unknown document {}", term, id));
+                }
+
+                if !self
+                    .doc_tokens
+                    .get(id)
+                    .is_some_and(|tokens| tokens.contains(term))
+                {
+                    problems.push(format!(
+                        "term {:?} lists document {} but its tokens don't include it",
+                        term, id
+                    ));
+                }
+            }
+        }
+
+        // 2. doc_tokens -> postings
+        for (id, tokens) in &self.doc_tokens {
+            for token in tokens {
+                if !self.postings.get(token).is_some_and(|ids| ids.contains(id)) {
+                    problems.push(format!(
+                        "document {} has token {:?} but no posting for it",
+                        id, token
+                    ));
+                }
+            }
+        }
+
+        // 3. Per-document maps only hold stored documents
+        let per_document: [(&str, Vec<&DocumentId>); 5] = [
+            ("doc_tokens", self.doc_tokens.keys().collect()),
+            ("term_freqs", self.term_freqs.keys().collect()),
+            ("positions", self.positions.keys().collect()),
+            ("doc_lengths", self.doc_lengths.keys().collect()),
+            ("indexed_at", self.indexed_at.keys().collect()),
+        ];
+        for (name, ids) in per_document {
+            for id in ids {
+                if !self.documents.contains_key(id) {
+                    problems.push(format!("{} has an entry for unknown document {}", name, id));
+                }
+            }
+        }
+
+        // 4. Documents -> doc_tokens, and the path lookup both ways
+        for (id, doc) in &self.documents {
+            if !self.doc_tokens.contains_key(id) {
+                problems.push(format!("document {} has no token set", id));
+            }
+
+            if !self
+                .path_to_ids
+                .get(&doc.path)
+                .is_some_and(|ids| ids.contains(id))
+            {
+                problems.push(format!(
+                    "document {} isn't reachable by its path {:?}",
+                    id, doc.path
+                ));
+            }
+        }
+
+        for (path, ids) in &self.path_to_ids {
+            if ids.is_empty() {
+                problems.push(format!("path {:?} has no documents", path));
+            }
+
+            for id in ids {
+                match self.documents.get(id) {
+                    Some(doc) if &doc.path == path => {}
+                    Some(_) => problems.push(format!(
+                        "path {:?} points at document {} with another path",
+                        path, id
+                    )),
+                    None => {
+                        problems.push(format!("path {:?} points at unknown document {}", path, id))
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+
+        problems.sort();
+        Err(problems)
+    }
+
+    /// `search_query`, but first checks that every posting the query touches
+    /// points at a stored document, returning `IndexError::Inconsistent`
+    /// instead of quietly working around a broken index.
+    ///
+    /// Only the query's own postings are checked (`verify` covers the rest),
+    /// so this is cheap enough for development builds and tests. The regular
+    /// search methods skip the check.
+    pub fn search_checked(&self, query: &str) -> Result<Vec<DocumentId>, IndexError> {
+        for token in self.query_tokens(query) {
+            let Some(ids) = self.postings.get(&token) else {
+                continue;
+            };
+
+            if let Some(ghost) = ids.iter().find(|id| !self.documents.contains_key(id)) {
+                return Err(IndexError::Inconsistent(format!(
+                    "term {:?} lists unknown document {}",
+                    token, ghost
+                )));
+            }
+        }
+
+        Ok(self.search_query(query))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_index_verifies_after_churn() {
+        let mut index = Index::new();
+        let ids: Vec<DocumentId> = (0..10)
+            .map(|i| index.add_text(&format!("shared words number{}", i)))
+            .collect();
+
+        for id in ids.iter().step_by(2) {
+            index.remove_document(*id);
+        }
+
+        assert_eq!(index.verify(), Ok(()));
+    }
+
+    #[test]
+    fn verify_reports_corruption() {
+        let mut index = Index::new();
+        let kept = index.add_text("alpha beta");
+        let ghost = DocumentId::random();
+
+        // Posting for a document that doesn't exist, and a token whose
+        // posting went missing
+        index.postings.get_mut("alpha").unwrap().insert(ghost);
+        index.postings.remove("beta");
+
+        let problems = index.verify().unwrap_err();
+
+        assert!(problems.contains(&format!("term \"alpha\" lists unknown document {}", ghost)));
+        assert!(problems.contains(&format!(
+            "document {} has token \"beta\" but no posting for it",
+            kept
+        )));
+    }
+
+    #[test]
+    fn checked_search_reports_orphaned_postings() {
+        let mut index = Index::new();
+        let kept = index.add_text("alpha beta");
+        assert_eq!(index.search_checked("alpha").unwrap(), vec![kept]);
+
+        let ghost = DocumentId::random();
+        index.postings.get_mut("alpha").unwrap().insert(ghost);
+
+        match index.search_checked("alpha") {
+            Err(IndexError::Inconsistent(problem)) => {
+                assert_eq!(
+                    problem,
+                    format!("term \"alpha\" lists unknown document {}", ghost)
+                )
+            }
+            other => panic!("expected an inconsistency, got {other:?}"),
+        }
+
+        // Queries that don't touch the broken posting still work
+        assert_eq!(index.search_checked("beta").unwrap(), vec![kept]);
+    }
+}
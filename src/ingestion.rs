@@ -1,29 +1,311 @@
+use std::collections::HashSet;
 use std::fs::read_to_string;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::time::SystemTime;
 use thiserror::Error;
 use uuid::Uuid;
 
+mod ignore;
+mod rfc3339;
+pub use ignore::{IGNORE_FILES, IgnoreRules};
+
+/// Identifies a document within an index.
+///
+/// A UUID underneath (and serialized as one, so index files are unchanged),
+/// but its own type so document ids can't be mixed up with other UUIDs.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Deserialize, serde::Serialize,
+)]
+#[serde(transparent)]
+pub struct DocumentId(Uuid);
+
+impl DocumentId {
+    /// A fresh random (v4) id.
+    pub fn random() -> Self {
+        DocumentId(Uuid::new_v4())
+    }
+
+    pub fn as_uuid(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl From<Uuid> for DocumentId {
+    fn from(uuid: Uuid) -> Self {
+        DocumentId(uuid)
+    }
+}
+
+impl From<DocumentId> for Uuid {
+    fn from(id: DocumentId) -> Self {
+        id.0
+    }
+}
+
+impl std::fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A plain file document with no mtime, for tests that only care about
+/// its path and content.
+#[cfg(test)]
+pub(crate) fn test_doc(path: impl AsRef<Path>, content: &str) -> Document {
+    test_doc_modified(path, content, None)
+}
+
+/// `test_doc` with a file mtime, for tests that compare it against disk.
+#[cfg(test)]
+pub(crate) fn test_doc_modified(
+    path: impl AsRef<Path>,
+    content: &str,
+    modified: Option<SystemTime>,
+) -> Document {
+    Document::file(path.as_ref().to_path_buf(), content.to_string(), modified)
+}
+
 // TODO: Making fields pub for now...will add getters leter to make more robust.
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Document {
-    pub id: Uuid,
+    pub id: DocumentId,
     pub path: PathBuf,
     pub content: String,
+    /// File mtime, serialized as an RFC 3339 string (older index files
+    /// held serde's `{secs_since_epoch, ...}` struct, which still loads).
+    #[serde(default, with = "rfc3339")]
     pub modified: Option<SystemTime>,
+    /// Set when this document is one chunk of a larger file.
+    #[serde(default)]
+    pub chunk: Option<ChunkInfo>,
+    /// Where the document came from. Indexes saved before this existed only
+    /// held files, hence the default.
+    #[serde(default)]
+    pub source: DocumentSource,
+    /// Language hint like `"en"` or `"ja"`, used to pick a tokenizer strategy
+    /// (see `IndexBuilder::language`). `None` means plain word tokenization.
+    #[serde(default)]
+    pub lang: Option<String>,
+}
+
+impl Document {
+    /// A whole (unchunked) file with a fresh id, no language hint and
+    /// `DocumentSource::File`. Override fields with struct update syntax,
+    /// e.g. `Document { id, ..Document::file(path, content, None) }`.
+    pub fn file(path: PathBuf, content: String, modified: Option<SystemTime>) -> Self {
+        Document {
+            id: DocumentId::random(),
+            path,
+            content,
+            modified,
+            chunk: None,
+            source: DocumentSource::File,
+            lang: None,
+        }
+    }
+}
+
+/// How a document entered the index.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize,
+)]
+pub enum DocumentSource {
+    /// Read from a file on disk (`load_documents`, the watcher, ...)
+    #[default]
+    File,
+    /// Piped in on stdin
+    Stdin,
+    /// Brought in through `Index::import_documents_ndjson`
+    Imported,
+    /// Handed over in memory, e.g. `Index::add_text`
+    Memory,
+}
+
+/// Where a chunk came from: the file it was cut out of and the byte offset
+/// of the chunk's first character inside that file's content.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct ChunkInfo {
+    pub parent_path: PathBuf,
+    pub offset: usize,
+}
+
+/// How to split a file into separately indexed chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStrategy {
+    /// Start a new chunk at every markdown heading line (`#`, `##`, ...).
+    Heading,
+    /// Split on blank lines.
+    Paragraph,
+    /// Fixed windows of this many characters.
+    Window(usize),
+}
+
+/// File extensions ingested when the caller doesn't pick their own.
+pub const DEFAULT_EXTENSIONS: &[&str] = &["md", "txt"];
+
+/// Knobs for `load_documents_with`. `Default` matches `load_documents`.
+#[derive(Debug, Clone)]
+pub struct LoadOptions {
+    /// When set, every file is split into chunks and each chunk becomes its
+    /// own `Document`.
+    pub chunk: Option<ChunkStrategy>,
+    /// Extensions (without the dot) to ingest. Compared case-insensitively.
+    pub extensions: Vec<String>,
+    /// Derive each id from the file's canonical path (see `path_id`) instead
+    /// of generating a random one, so re-indexing a file keeps its id.
+    pub deterministic_ids: bool,
+    /// Skip zero-byte files without reading them; they'd only add documents
+    /// with no tokens. On by default.
+    pub skip_empty: bool,
+    /// Skip files matched by `dir`'s `.gitignore` or `.searchignore` (see
+    /// `IgnoreRules`), the same rules the watcher applies to live events.
+    /// On by default.
+    pub respect_ignore_files: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            chunk: None,
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            deterministic_ids: false,
+            skip_empty: true,
+            respect_ignore_files: true,
+        }
+    }
+}
+
+// Namespace for path-derived document ids (a UUIDv4 picked once, never change it)
+const PATH_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a9e_54b7_4d3a_9e02_b8d1_7c4f_a315);
+
+/// Stable id for the file at `path`: a UUIDv5 of its canonical path, so the
+/// same file maps to the same id across runs. Falls back to the normalized
+/// path when it can't be canonicalized (e.g. it no longer exists).
+pub fn path_id(path: &Path) -> DocumentId {
+    let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| normalize_path(path));
+    DocumentId(Uuid::new_v5(
+        &PATH_ID_NAMESPACE,
+        canonical.to_string_lossy().as_bytes(),
+    ))
+}
+
+/// Cleans up how `path` is spelled without touching the filesystem: drops
+/// `.` segments, folds `name/..` pairs and rebuilds it with the platform's
+/// separator. So `./notes/a.txt`, `notes//a.txt` and `notes/x/../a.txt` all
+/// come out as `notes/a.txt`.
+///
+/// Purely lexical on purpose: it gives the same answer before and after the
+/// file is deleted, which a watcher's remove events rely on. The flip side
+/// is that `link/..` is folded even when `link` is a symlink. A leading `..`
+/// is kept as is.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `/..` is just `/`
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other),
+        }
+    }
+
+    normalized
+}
+
+/// Returns true when `path` ends in one of `extensions`, ignoring case so
+/// `NOTE.TXT` counts as a `txt` file.
+pub fn has_allowed_extension<S: AsRef<str>>(path: &Path, extensions: &[S]) -> bool {
+    let Some(ext) = path.extension().and_then(|e| e.to_str()) else {
+        return false;
+    };
+
+    extensions
+        .iter()
+        .any(|allowed| allowed.as_ref().eq_ignore_ascii_case(ext))
+}
+
+/// Reads a text file the way every ingestion path should see it: see
+/// `normalize_content`.
+pub fn read_content(path: &Path) -> std::io::Result<String> {
+    read_to_string(path).map(|raw| normalize_content(&raw))
+}
+
+/// Drops a leading UTF-8 byte-order mark and turns CRLF / lone CR line
+/// endings into LF, so files saved on Windows tokenize and slice exactly like
+/// everything else.
+pub fn normalize_content(raw: &str) -> String {
+    let text = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+
+    if !text.contains('\r') {
+        return text.to_string();
+    }
+
+    text.replace("\r\n", "\n").replace('\r', "\n")
 }
 
 #[derive(Debug, Error)]
 pub enum IngestError {
     #[error("path is not a directory")]
     NotDirectory,
+    #[error("path is not a file")]
+    NotFile,
+    /// The file's extension isn't one we ingest (see `DEFAULT_EXTENSIONS`).
+    #[error("unsupported file type: {0}")]
+    UnsupportedExtension(PathBuf),
     /// Wrap any underlying I/O error. `#[from]` creates `From<std::io::Error>`,
     /// which lets `?` convert `std::io::Error` -> `IngestError::Io(...)` automatically.
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
 
+/// Ingests a single `.md` / `.txt` file, for when a whole directory is
+/// overkill.
+pub fn load_document(path: &Path) -> Result<Document, IngestError> {
+    // 1. Must be an actual file with an extension we understand
+    if !path.is_file() {
+        return Err(IngestError::NotFile);
+    }
+
+    if !has_allowed_extension(path, DEFAULT_EXTENSIONS) {
+        return Err(IngestError::UnsupportedExtension(path.to_path_buf()));
+    }
+
+    // 2. Read it, same as a directory entry would be
+    let content = read_content(path)?;
+    let modified = std::fs::metadata(path).ok().and_then(|m| m.modified().ok());
+
+    Ok(Document::file(path.to_path_buf(), content, modified))
+}
+
 pub fn load_documents(dir: &Path) -> Result<Vec<Document>, IngestError> {
+    load_documents_with(dir, &LoadOptions::default())
+}
+
+pub fn load_documents_with(
+    dir: &Path,
+    options: &LoadOptions,
+) -> Result<Vec<Document>, IngestError> {
+    load_documents_with_progress(dir, options, |_, _| {})
+}
+
+/// `load_documents_with`, calling `progress(done, total)` after each file is
+/// read so a CLI can draw a progress bar.
+///
+/// `total` comes from a first pass over the directory listing, so it's known
+/// from the first call; `done` counts up from 1 to `total`, once per file
+/// (not per chunk).
+pub fn load_documents_with_progress<F: FnMut(usize, usize)>(
+    dir: &Path,
+    options: &LoadOptions,
+    mut progress: F,
+) -> Result<Vec<Document>, IngestError> {
     // 1. Ensure the path is a directory
     if !dir.is_dir() {
         return Err(IngestError::NotDirectory);
@@ -33,10 +315,14 @@ pub fn load_documents(dir: &Path) -> Result<Vec<Document>, IngestError> {
     // Before: std::fs::read_dir(dir).map_err(IngestError::Io)?
     // Now: the `?` will convert `std::io::Error` -> `IngestError` via `From attribute
     let entries = std::fs::read_dir(dir)?;
+    let ignore = if options.respect_ignore_files {
+        IgnoreRules::load(dir)?
+    } else {
+        IgnoreRules::parse(dir, "")
+    };
 
-    let mut docs = Vec::new();
-
-    // 3. Process each entry
+    // 3. Find the files to ingest first, so the total is known up front
+    let mut files = Vec::new();
     for entry_result in entries {
         let entry = entry_result?;
         let path = entry.path();
@@ -47,35 +333,172 @@ pub fn load_documents(dir: &Path) -> Result<Vec<Document>, IngestError> {
             continue;
         }
 
-        // 4. Only allow .md or .txt files
-        let is_text = matches!(
-            path.extension().and_then(|e| e.to_str()),
-            Some("md" | "txt")
-        );
+        // 4. Only allow the configured extensions (.md / .txt by default),
+        //    minus whatever the ignore files exclude
+        if !has_allowed_extension(&path, &options.extensions) || ignore.is_ignored(&path) {
+            continue;
+        }
 
-        if !is_text {
+        // 5. Metadata first: empty files can be skipped without a read.
+        // Ignore metadata errors and dont fail the whole load:
+        let metadata = entry.metadata().ok();
+        if options.skip_empty && metadata.as_ref().is_some_and(|m| m.len() == 0) {
             continue;
         }
 
-        // 5. Read the file contents (propagates io::Error -> IngestError::Io)
-        let content = read_to_string(&path)?;
+        files.push((path, metadata));
+    }
+
+    let total = files.len();
+    let mut docs = Vec::new();
 
-        // 6. Get modified time
-        // Ignore metadata errors and dont fail the whole load:
-        let modified = entry.metadata().ok().and_then(|m| m.modified().ok());
+    for (done, (path, metadata)) in files.into_iter().enumerate() {
+        // 6. Read the file contents (propagates io::Error -> IngestError::Io)
+        let content = read_content(&path)?;
+        let modified = metadata.and_then(|m| m.modified().ok());
 
         // 7. Build the document
-        docs.push(Document {
-            id: Uuid::new_v4(),
-            path,
-            content,
-            modified,
-        });
+        let mut doc = Document::file(path, content, modified);
+        if options.deterministic_ids {
+            doc.id = path_id(&doc.path);
+        }
+
+        // 8. Optionally split it up so each section is searchable on its own
+        //    (stable chunk ids hang off the file's id and the chunk offset)
+        match options.chunk {
+            Some(strategy) => {
+                let mut chunks = chunk_document(&doc, strategy);
+                if options.deterministic_ids {
+                    for chunk in &mut chunks {
+                        let offset = chunk.chunk.as_ref().map_or(0, |info| info.offset);
+                        chunk.id = DocumentId(Uuid::new_v5(
+                            doc.id.as_uuid(),
+                            offset.to_string().as_bytes(),
+                        ));
+                    }
+                }
+                docs.extend(chunks);
+            }
+            None => docs.push(doc),
+        }
+
+        progress(done + 1, total);
     }
 
     Ok(docs)
 }
 
+/// What `load_documents_many` managed to load, and which roots failed.
+#[derive(Debug, Default)]
+pub struct MultiLoad {
+    pub documents: Vec<Document>,
+    /// One entry per root that couldn't be loaded, in the order given
+    pub errors: Vec<(PathBuf, IngestError)>,
+}
+
+/// `load_documents` over several roots at once.
+///
+/// A file reachable from more than one root (overlapping roots, a symlinked
+/// directory, ...) is only loaded once, keyed on its canonical path. The
+/// first root that reaches it wins. A root that fails doesn't stop the
+/// others; its error is collected in `MultiLoad::errors`.
+pub fn load_documents_many(dirs: &[PathBuf]) -> MultiLoad {
+    let mut load = MultiLoad::default();
+    let mut seen = HashSet::new();
+
+    for dir in dirs {
+        let docs = match load_documents(dir) {
+            Ok(docs) => docs,
+            Err(e) => {
+                load.errors.push((dir.clone(), e));
+                continue;
+            }
+        };
+
+        for doc in docs {
+            let canonical =
+                std::fs::canonicalize(&doc.path).unwrap_or_else(|_| normalize_path(&doc.path));
+            if seen.insert(canonical) {
+                load.documents.push(doc);
+            }
+        }
+    }
+
+    load
+}
+
+/// Splits `doc` into one `Document` per chunk.
+///
+/// Each chunk gets a fresh id, a `path` of `<parent>#<offset>` so chunks stay
+/// distinct in the index, and a `ChunkInfo` pointing back at the parent file.
+/// Whitespace-only chunks are dropped.
+pub fn chunk_document(doc: &Document, strategy: ChunkStrategy) -> Vec<Document> {
+    chunk_ranges(&doc.content, strategy)
+        .into_iter()
+        .filter(|(start, end)| !doc.content[*start..*end].trim().is_empty())
+        .map(|(start, end)| Document {
+            id: DocumentId::random(),
+            path: PathBuf::from(format!("{}#{}", doc.path.display(), start)),
+            content: doc.content[start..end].to_string(),
+            modified: doc.modified,
+            chunk: Some(ChunkInfo {
+                parent_path: doc.path.clone(),
+                offset: start,
+            }),
+            source: doc.source,
+            lang: doc.lang.clone(),
+        })
+        .collect()
+}
+
+// Byte ranges (always on char boundaries) covering `content` in order.
+fn chunk_ranges(content: &str, strategy: ChunkStrategy) -> Vec<(usize, usize)> {
+    let mut starts = vec![0];
+
+    match strategy {
+        ChunkStrategy::Heading => {
+            let mut line_start = 0;
+            for line in content.split_inclusive('\n') {
+                if line_start > 0 && line.trim_start().starts_with('#') {
+                    starts.push(line_start);
+                }
+                line_start += line.len();
+            }
+        }
+        ChunkStrategy::Paragraph => {
+            // A paragraph starts on the first non-blank line after a blank one
+            let mut line_start = 0;
+            let mut previous_blank = false;
+            for line in content.split_inclusive('\n') {
+                let blank = line.trim().is_empty();
+                if previous_blank && !blank {
+                    starts.push(line_start);
+                }
+                previous_blank = blank;
+                line_start += line.len();
+            }
+        }
+        ChunkStrategy::Window(size) => {
+            // A zero window would never advance, treat it as "no chunking"
+            if size > 0 {
+                starts.extend(
+                    content
+                        .char_indices()
+                        .map(|(i, _)| i)
+                        .enumerate()
+                        .filter(|(n, _)| *n > 0 && n % size == 0)
+                        .map(|(_, i)| i),
+                );
+            }
+        }
+    }
+
+    let mut ends: Vec<usize> = starts.iter().skip(1).copied().collect();
+    ends.push(content.len());
+
+    starts.into_iter().zip(ends).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*; // Bring everything from the outer module into scope for testing
@@ -143,4 +566,302 @@ mod tests {
             _ => panic!("Expected NotDirectory"),
         }
     }
+
+    #[test]
+    fn test_load_single_document() {
+        let dir_path = make_temp_dir("rust_test_single");
+        let file_path = dir_path.join("note.md");
+        fs::write(&file_path, "just one note").unwrap();
+
+        let doc = load_document(&file_path).unwrap();
+        assert_eq!(doc.path, file_path);
+        assert_eq!(doc.content, "just one note");
+        assert!(doc.modified.is_some());
+
+        // A directory is not a single document
+        assert!(matches!(
+            load_document(&dir_path),
+            Err(IngestError::NotFile)
+        ));
+    }
+
+    #[test]
+    fn test_bom_and_crlf_are_normalized() {
+        let dir_path = make_temp_dir("rust_test_windows");
+        let file_path = dir_path.join("windows.txt");
+        fs::write(&file_path, "\u{feff}Heading\r\nline two\rline three\r\n").unwrap();
+
+        let doc = load_document(&file_path).unwrap();
+        assert_eq!(doc.content, "Heading\nline two\nline three\n");
+        assert_eq!(crate::tokenizer::tokenize(&doc.content)[0], "heading");
+
+        // Directory loading goes through the same path
+        let docs = load_documents(&dir_path).unwrap();
+        assert_eq!(docs[0].content, doc.content);
+    }
+
+    #[test]
+    fn test_load_single_document_rejects_non_text_files() {
+        let dir_path = make_temp_dir("rust_test_single");
+        let file_path = dir_path.join("picture.png");
+        fs::write(&file_path, "binary").unwrap();
+
+        match load_document(&file_path) {
+            Err(IngestError::UnsupportedExtension(path)) => assert_eq!(path, file_path),
+            other => panic!("Expected UnsupportedExtension, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ignore_files_are_respected_unless_turned_off() {
+        let dir_path = make_temp_dir("rust_test_ignore");
+        fs::write(dir_path.join(".gitignore"), "*.draft.md\n").unwrap();
+        fs::write(dir_path.join("idea.draft.md"), "half an idea").unwrap();
+        fs::write(dir_path.join("idea.md"), "a whole idea").unwrap();
+
+        let docs = load_documents(&dir_path).unwrap();
+        let paths: Vec<_> = docs.iter().map(|doc| doc.path.clone()).collect();
+        assert_eq!(paths, vec![dir_path.join("idea.md")]);
+
+        let options = LoadOptions {
+            respect_ignore_files: false,
+            ..LoadOptions::default()
+        };
+        assert_eq!(load_documents_with(&dir_path, &options).unwrap().len(), 2);
+
+        fs::remove_dir_all(dir_path).unwrap();
+    }
+
+    #[test]
+    fn test_paragraph_chunks_are_independently_searchable() {
+        let dir_path = make_temp_dir("rust_test_chunks");
+        let file_path = dir_path.join("long.md");
+        let content = "Rust ownership rules\n\nAsync runtimes and executors\n\n\nClosing thoughts";
+        fs::write(&file_path, content).unwrap();
+
+        let options = LoadOptions {
+            chunk: Some(ChunkStrategy::Paragraph),
+            ..LoadOptions::default()
+        };
+        let docs = load_documents_with(&dir_path, &options).unwrap();
+
+        assert_eq!(docs.len(), 3);
+
+        // Every chunk points back at the file and at where it starts
+        for doc in &docs {
+            let info = doc.chunk.as_ref().unwrap();
+            assert_eq!(info.parent_path, file_path);
+            assert!(content[info.offset..].starts_with(doc.content.trim_end()));
+        }
+
+        let mut index = crate::index::Index::new();
+        for doc in docs.iter().cloned() {
+            index.add_document(doc);
+        }
+
+        // Each query only hits the chunk that actually talks about it
+        let ownership = index.search_query("ownership");
+        let runtimes = index.search_query("runtimes");
+        assert_eq!(ownership.len(), 1);
+        assert_eq!(runtimes.len(), 1);
+        assert_ne!(ownership, runtimes);
+
+        let runtime_chunk = docs.iter().find(|d| d.id == runtimes[0]).unwrap();
+        assert_eq!(runtime_chunk.chunk.as_ref().unwrap().offset, 22);
+    }
+
+    #[test]
+    fn test_custom_extensions() {
+        let dir_path = make_temp_dir("rust_test_extensions");
+        fs::write(dir_path.join("agenda.org"), "* TODO write tests").unwrap();
+        fs::write(dir_path.join("LOUD.TXT"), "shouting").unwrap();
+
+        // Defaults skip .org but still match .TXT regardless of case
+        let docs = load_documents(&dir_path).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].content, "shouting");
+
+        // Opting in to org picks it up
+        let options = LoadOptions {
+            extensions: vec!["org".to_string(), "txt".to_string()],
+            ..LoadOptions::default()
+        };
+        let docs = load_documents_with(&dir_path, &options).unwrap();
+        assert_eq!(docs.len(), 2);
+        assert!(docs.iter().any(|doc| doc.content == "* TODO write tests"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn load_documents_many_skips_files_seen_through_another_root() {
+        let base = make_temp_dir("many_roots");
+        let (first, second) = (base.join("first"), base.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(first.join("a.md"), "alpha").unwrap();
+        fs::write(second.join("b.md"), "beta").unwrap();
+
+        // Same directory again, through a symlink and a roundabout spelling
+        let linked = base.join("linked");
+        std::os::unix::fs::symlink(&first, &linked).unwrap();
+        let missing = base.join("missing");
+
+        let load = load_documents_many(&[
+            first.clone(),
+            second.clone(),
+            linked,
+            second.join("..").join("first"),
+            missing.clone(),
+        ]);
+
+        let mut paths: Vec<PathBuf> = load.documents.iter().map(|doc| doc.path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec![first.join("a.md"), second.join("b.md")]);
+
+        assert_eq!(load.errors.len(), 1);
+        assert_eq!(load.errors[0].0, missing);
+        assert!(matches!(load.errors[0].1, IngestError::NotDirectory));
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn progress_is_reported_once_per_file() {
+        let dir_path = make_temp_dir("load_progress");
+        for name in ["a.md", "b.txt", "c.md"] {
+            fs::write(dir_path.join(name), "some notes").unwrap();
+        }
+        fs::write(dir_path.join("empty.md"), "").unwrap();
+        fs::write(dir_path.join("image.png"), "not text").unwrap();
+
+        let mut calls = Vec::new();
+        let docs =
+            load_documents_with_progress(&dir_path, &LoadOptions::default(), |done, total| {
+                calls.push((done, total))
+            })
+            .unwrap();
+
+        assert_eq!(docs.len(), 3);
+        assert_eq!(calls, vec![(1, 3), (2, 3), (3, 3)]);
+
+        fs::remove_dir_all(&dir_path).unwrap();
+    }
+
+    #[test]
+    fn zero_byte_files_are_skipped_by_default() {
+        let dir_path = std::env::temp_dir().join(format!("skip_empty_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir_path).unwrap();
+        fs::write(dir_path.join("empty.txt"), "").unwrap();
+        fs::write(dir_path.join("full.txt"), "some words").unwrap();
+
+        let docs = load_documents(&dir_path).unwrap();
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].path, dir_path.join("full.txt"));
+
+        // Opting out keeps them
+        let options = LoadOptions {
+            skip_empty: false,
+            ..LoadOptions::default()
+        };
+        assert_eq!(load_documents_with(&dir_path, &options).unwrap().len(), 2);
+
+        fs::remove_dir_all(&dir_path).unwrap();
+    }
+
+    #[test]
+    fn deterministic_ids_survive_reindexing() {
+        let dir_path = std::env::temp_dir().join(format!("stable_ids_{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir_path).unwrap();
+        fs::write(dir_path.join("a.md"), "first\n\nsecond").unwrap();
+        fs::write(dir_path.join("b.txt"), "other").unwrap();
+
+        let sorted_ids = |options: &LoadOptions| {
+            let mut ids: Vec<DocumentId> = load_documents_with(&dir_path, options)
+                .unwrap()
+                .iter()
+                .map(|doc| doc.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+
+        let stable = LoadOptions {
+            deterministic_ids: true,
+            ..LoadOptions::default()
+        };
+        let first = sorted_ids(&stable);
+        assert_eq!(first, sorted_ids(&stable));
+        assert!(first.contains(&path_id(&dir_path.join("a.md"))));
+
+        // Chunks are stable too, and distinct from each other
+        let chunked = LoadOptions {
+            chunk: Some(ChunkStrategy::Paragraph),
+            ..stable
+        };
+        let chunks = sorted_ids(&chunked);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks, sorted_ids(&chunked));
+
+        // Random ids stay the default
+        assert_ne!(sorted_ids(&LoadOptions::default()), first);
+
+        fs::remove_dir_all(&dir_path).unwrap();
+    }
+
+    #[test]
+    fn normalize_path_folds_dots_and_separators() {
+        let cases = [
+            ("./notes/a.txt", "notes/a.txt"),
+            ("notes//a.txt", "notes/a.txt"),
+            ("notes/x/../a.txt", "notes/a.txt"),
+            ("/notes/./a.txt", "/notes/a.txt"),
+            ("/../a.txt", "/a.txt"),
+            ("../a.txt", "../a.txt"),
+            ("a/../../b.txt", "../b.txt"),
+        ];
+
+        for (raw, expected) in cases {
+            assert_eq!(
+                normalize_path(Path::new(raw)),
+                PathBuf::from(expected),
+                "{raw}"
+            );
+        }
+    }
+
+    #[test]
+    fn document_id_round_trips_through_uuid() {
+        let uuid = Uuid::new_v4();
+        let id = DocumentId::from(uuid);
+
+        assert_eq!(Uuid::from(id), uuid);
+        assert_eq!(id.to_string(), uuid.to_string());
+
+        // Serialized exactly like the bare uuid, so old index files still load
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, serde_json::to_string(&uuid).unwrap());
+        assert_eq!(serde_json::from_str::<DocumentId>(&json).unwrap(), id);
+    }
+
+    #[test]
+    fn test_heading_and_window_chunking() {
+        let doc = test_doc("notes.md", "# One\nfirst\n## Two\nsecond");
+
+        let headings = chunk_document(&doc, ChunkStrategy::Heading);
+        assert_eq!(headings.len(), 2);
+        assert_eq!(headings[0].content, "# One\nfirst\n");
+        assert_eq!(headings[1].content, "## Two\nsecond");
+        assert_eq!(headings[1].path, PathBuf::from("notes.md#12"));
+
+        // Windows count chars, so multi-byte text never gets cut mid-char
+        let accented = Document {
+            content: "éééééé".to_string(),
+            ..doc
+        };
+        let windows = chunk_document(&accented, ChunkStrategy::Window(4));
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].content, "éééé");
+        assert_eq!(windows[1].content, "éé");
+        assert_eq!(windows[1].chunk.as_ref().unwrap().offset, 8);
+    }
 }
@@ -0,0 +1,222 @@
+//! `.gitignore`-style ignore files, so ignored paths (build output, drafts)
+//! stay out of the index.
+
+use super::normalize_path;
+use glob::{MatchOptions, Pattern};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Ignore files read by `IgnoreRules::load`, in order (later rules win).
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".searchignore"];
+
+// Git's matching: `*` doesn't cross `/`, only `**` does
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Ignore patterns for the files under one root directory.
+///
+/// Supports the common subset of `.gitignore`: `#` comments, `!` to
+/// re-include, a trailing `/` for directories only, and patterns containing
+/// a `/` being relative to the root (`*`, `?`, `[..]` and `**` globs). Only
+/// the root's own ignore files are read, not ones in subdirectories.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    root: PathBuf,
+    // `root` canonicalized, for matching absolute paths against a relative root
+    canonical_root: Option<PathBuf>,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    // Matched against the whole relative path rather than just the name
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// Rules parsed from `text` (the contents of an ignore file) for paths
+    /// under `root`. Lines that aren't valid patterns are skipped.
+    pub fn parse(root: &Path, text: &str) -> Self {
+        let mut rules = IgnoreRules {
+            root: normalize_path(root),
+            canonical_root: root.canonicalize().ok(),
+            rules: Vec::new(),
+        };
+        rules.extend(text);
+        rules
+    }
+
+    /// Reads every file in `IGNORE_FILES` that exists in `root`. Missing
+    /// files are fine (no rules); other read errors are returned.
+    pub fn load(root: &Path) -> io::Result<Self> {
+        let mut rules = IgnoreRules::parse(root, "");
+
+        for name in IGNORE_FILES {
+            match fs::read_to_string(root.join(name)) {
+                Ok(text) => rules.extend(&text),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// True when `path` (absolute, or relative to the working directory
+    /// like `root`) is ignored, either itself or through an ignored parent
+    /// directory. Paths outside `root` are never ignored.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let Some(relative) = self.relative(path) else {
+            return false;
+        };
+
+        // 1. An ignored directory hides everything in it, like git, so walk
+        //    down from the top and stop at the first ignored ancestor
+        let components: Vec<_> = relative.components().collect();
+        let mut current = PathBuf::new();
+
+        for (i, component) in components.iter().enumerate() {
+            current.push(component);
+            let is_dir = i + 1 < components.len() || path.is_dir();
+
+            if self.matches(&current, is_dir) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn extend(&mut self, text: &str) {
+        for line in text.lines() {
+            if let Some(rule) = Rule::parse(line) {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    fn relative(&self, path: &Path) -> Option<PathBuf> {
+        let path = normalize_path(path);
+
+        if let Ok(relative) = path.strip_prefix(&self.root) {
+            return Some(relative.to_path_buf());
+        }
+
+        // Watch events usually carry absolute paths even for a relative root
+        let canonical_root = self.canonical_root.as_ref()?;
+        path.strip_prefix(canonical_root)
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    // The last matching rule decides, so `!keep.md` can undo `*.md`
+    fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        let name = relative.file_name().map(Path::new).unwrap_or(relative);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && rule.pattern.matches_path_with(
+                        if rule.anchored { relative } else { name },
+                        MATCH_OPTIONS,
+                    )
+            })
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+impl Rule {
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        // A `/` anywhere but the end ties the pattern to the root
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        // `\#` and `\!` escape a literal first character
+        let line = line.strip_prefix('\\').unwrap_or(line);
+
+        Some(Rule {
+            pattern: Pattern::new(line).ok()?,
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn gitignore_patterns_match_like_git() {
+        let rules = IgnoreRules::parse(
+            Path::new("notes"),
+            "# build output\ntarget/\n*.draft.md\n/private\ndocs/**/tmp.md\n!keep.draft.md\n\n",
+        );
+
+        let ignored = |path: &str| rules.is_ignored(Path::new(path));
+
+        assert!(ignored("notes/target/debug/readme.md"));
+        assert!(ignored("notes/deep/target/out.md"));
+        assert!(ignored("notes/ideas.draft.md"));
+        assert!(ignored("notes/sub/ideas.draft.md"));
+        assert!(ignored("./notes/private/diary.md"));
+        assert!(ignored("notes/docs/a/b/tmp.md"));
+
+        assert!(!ignored("notes/keep.draft.md"));
+        assert!(!ignored("notes/sub/private/diary.md"));
+        assert!(!ignored("notes/target.md"));
+        assert!(!ignored("notes/ideas.md"));
+        // Outside the root
+        assert!(!ignored("elsewhere/ideas.draft.md"));
+    }
+
+    #[test]
+    fn load_reads_ignore_files_from_the_root() {
+        let root = std::env::temp_dir().join(format!("ignore_{}", Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+
+        assert!(IgnoreRules::load(&root).unwrap().is_empty());
+
+        fs::write(root.join(".gitignore"), "*.tmp.md\n").unwrap();
+        fs::write(root.join(".searchignore"), "journal/\n").unwrap();
+        let rules = IgnoreRules::load(&root).unwrap();
+
+        assert!(rules.is_ignored(&root.join("scratch.tmp.md")));
+        assert!(rules.is_ignored(&root.join("journal/monday.md")));
+        assert!(!rules.is_ignored(&root.join("rust.md")));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
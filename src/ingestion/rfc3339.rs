@@ -0,0 +1,90 @@
+//! `#[serde(with = "rfc3339")]` for `Option<SystemTime>`: written as an
+//! RFC 3339 string like `"2024-05-01T09:30:00.123456789Z"` (or `null`)
+//! rather than serde's opaque `{secs_since_epoch, nanos_since_epoch}`.
+//!
+//! Reading accepts both, so index files saved before keep loading.
+
+use serde::{Deserialize, Deserializer, Serializer, de::Error as _, ser::Error as _};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn serialize<S: Serializer>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let Some(time) = time else {
+        return serializer.serialize_none();
+    };
+
+    // Same limit serde has for SystemTime (and humantime would panic)
+    if *time < UNIX_EPOCH {
+        return Err(S::Error::custom("SystemTime must be later than UNIX_EPOCH"));
+    }
+
+    // Nanosecond precision so it reads back as the exact same time
+    serializer.collect_str(&humantime::format_rfc3339_nanos(*time))
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<SystemTime>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Timestamp {
+        Text(String),
+        Legacy(SystemTime),
+    }
+
+    match Option::<Timestamp>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Timestamp::Legacy(time)) => Ok(Some(time)),
+        Some(Timestamp::Text(text)) => humantime::parse_rfc3339(&text)
+            .map(Some)
+            .map_err(|e| D::Error::custom(format!("invalid timestamp {text:?}: {e}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Serialize;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Stamped {
+        #[serde(with = "super")]
+        at: Option<SystemTime>,
+    }
+
+    #[test]
+    fn timestamps_are_readable_and_round_trip() {
+        let at = UNIX_EPOCH + Duration::new(1_714_555_800, 123_456_789);
+        let stamped = Stamped { at: Some(at) };
+
+        let json = serde_json::to_string(&stamped).unwrap();
+        assert_eq!(json, r#"{"at":"2024-05-01T09:30:00.123456789Z"}"#);
+        assert_eq!(serde_json::from_str::<Stamped>(&json).unwrap(), stamped);
+
+        let none = Stamped { at: None };
+        let json = serde_json::to_string(&none).unwrap();
+        assert_eq!(json, r#"{"at":null}"#);
+        assert_eq!(serde_json::from_str::<Stamped>(&json).unwrap(), none);
+
+        // Right now round-trips too
+        let now = Stamped {
+            at: Some(SystemTime::now()),
+        };
+        let json = serde_json::to_string(&now).unwrap();
+        assert_eq!(serde_json::from_str::<Stamped>(&json).unwrap(), now);
+    }
+
+    #[test]
+    fn legacy_struct_timestamps_still_load() {
+        let json = r#"{"at":{"secs_since_epoch":1714555800,"nanos_since_epoch":5}}"#;
+
+        assert_eq!(
+            serde_json::from_str::<Stamped>(json).unwrap().at,
+            Some(UNIX_EPOCH + Duration::new(1_714_555_800, 5))
+        );
+        assert!(serde_json::from_str::<Stamped>(r#"{"at":"yesterday"}"#).is_err());
+    }
+}
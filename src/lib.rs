@@ -0,0 +1,6 @@
+pub mod federation;
+pub mod index;
+pub mod ingestion;
+pub mod search;
+pub mod tokenizer;
+pub mod watcher;
@@ -1,19 +1,15 @@
-mod index;
-mod ingestion;
-mod search;
-mod tokenizer;
-mod watcher;
-use crate::index::Index;
-use crate::ingestion::Document;
-use crate::tokenizer::tokenize;
-use crate::watcher::IndexEvent;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use rust_knowledge_search::index::{Index, MmapIndex, ReconcileReport};
+use rust_knowledge_search::ingestion::{self, DocumentId, DocumentSource, IngestError};
+use rust_knowledge_search::tokenizer::{tokenize, tokenize_with};
+use rust_knowledge_search::watcher::{self, IndexEvent};
 use std::{
+    collections::HashMap,
     fs,
-    sync::{Arc, Mutex, mpsc::Receiver},
-    time::SystemTime,
+    io::{self, IsTerminal, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
-use uuid::Uuid;
 
 #[derive(Parser, Debug)]
 #[command(name = "rust-search")]
@@ -28,142 +24,411 @@ struct Cli {
 #[derive(Subcommand, Debug)]
 enum Commands {
     Search {
-        /// The search query
-        query: String,
+        /// The search query, read from stdin when omitted
+        query: Option<String>,
+        /// Print only the number of matching documents
+        #[arg(long)]
+        count: bool,
+        /// Match documents with any query term (or) or all of them (and)
+        #[arg(long, value_enum, default_value_t = SearchMode::Or)]
+        mode: SearchMode,
+        /// Index file to search; picked from what's on disk when omitted
+        #[arg(long, value_enum)]
+        index_format: Option<IndexFormat>,
     },
+    /// Index a single note, or every note in a directory
+    Index {
+        /// File or directory to index
+        path: PathBuf,
+        /// Also write the binary search index next to the JSON one
+        #[arg(long, value_enum, default_value_t = IndexFormat::Json)]
+        index_format: IndexFormat,
+    },
+    /// Index text piped on stdin as a new document
+    IndexStdin,
+    /// Export stored documents as newline-delimited JSON
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Show how a file is tokenized, to debug why it does or doesn't match
+    Tokens {
+        /// The file to tokenize
+        file: PathBuf,
+        /// Print each distinct token once with how often it occurs
+        #[arg(long)]
+        counts: bool,
+    },
+    /// Catch up on changes to ./notes, then keep the index updated as they happen
+    Watch {
+        /// Also rewrite the binary search index on every save; when omitted,
+        /// only if one already exists
+        #[arg(long, value_enum)]
+        index_format: Option<IndexFormat>,
+    },
+}
+
+// How multi-term queries combine
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum SearchMode {
+    /// Any term (`search_query`)
+    Or,
+    /// Every term (`search_all`)
+    And,
+}
+
+// On-disk index formats
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum IndexFormat {
+    /// The full index as JSON (`index.json`)
+    Json,
+    /// The memory-mapped search layout (`index.bin`). It has no document
+    /// content and can't be updated, so `index.json` is always kept as well.
+    Binary,
+    // No compressed (gzip) format: it would need a compression dependency
+    // for a file that's already small next to the notes it indexes
 }
 
 const INDEX_PATH: &str = "index.json";
+const BINARY_INDEX_PATH: &str = "index.bin";
+
+// Prints the library's warnings and errors (e.g. from the watcher) to stderr
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}: {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
 
 fn main() {
     let cli = Cli::parse();
 
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(log::LevelFilter::Warn);
+    }
+
     // Load index if exists else create as new
     let index = Index::load_from_disk(INDEX_PATH).unwrap_or_else(|_| Index::new());
 
     // create shared index - shared across threads...neat :D
     let shared_index = Arc::new(Mutex::new(index));
 
-    // DOnt care about message just the event
-    let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel::<()>();
-
-    // start watcher
-    let _watcher_channel = create_watcher_channel(Arc::clone(&shared_index), shutdown_rx);
-
     // handle CLI commands
     match cli.command {
-        Commands::Search { query } => {
-            run_search(query, Arc::clone(&shared_index));
+        Commands::Search {
+            query,
+            count,
+            mode,
+            index_format,
+        } => {
+            // `echo "rust async" | rust-search search` works too
+            let Some(query) = query.or_else(read_query_from_stdin) else {
+                if let Some(search) = Cli::command().find_subcommand_mut("search") {
+                    let _ = search.print_help();
+                }
+                return;
+            };
+
+            // Binary if asked for, or if an up to date one is lying around
+            let binary = match index_format {
+                Some(IndexFormat::Json) => None,
+                Some(IndexFormat::Binary) => match Index::open_mmap(BINARY_INDEX_PATH) {
+                    Ok(view) => Some(view),
+                    Err(e) => {
+                        eprintln!("Failed to open {}: {}", BINARY_INDEX_PATH, e);
+                        return;
+                    }
+                },
+                None => detect_binary_index(),
+            };
+
+            match binary {
+                Some(view) => run_search(query, count, mode, SearchTarget::Binary(&view)),
+                None => {
+                    let index = shared_index.lock().unwrap();
+                    run_search(query, count, mode, SearchTarget::Full(&index));
+                }
+            }
+        }
+        Commands::Index { path, index_format } => {
+            run_index(&path, index_format, Arc::clone(&shared_index));
+        }
+        Commands::IndexStdin => {
+            run_index_stdin(Arc::clone(&shared_index));
+        }
+        Commands::Export { output } => {
+            run_export(output, Arc::clone(&shared_index));
+        }
+        Commands::Tokens { file, counts } => {
+            run_tokens(&file, counts, Arc::clone(&shared_index));
+        }
+        Commands::Watch { index_format } => {
+            // An existing index.bin is kept in step, or searches would pick
+            // up a stale one
+            let binary = match index_format {
+                Some(format) => format == IndexFormat::Binary,
+                None => Path::new(BINARY_INDEX_PATH).exists(),
+            };
+            run_watch(binary, Arc::clone(&shared_index));
+        }
+    }
+}
+
+// The query piped on stdin, or None if stdin is an interactive terminal
+// (nobody is going to type a query there without being asked to).
+fn read_query_from_stdin() -> Option<String> {
+    let mut stdin = io::stdin();
+    if stdin.is_terminal() {
+        return None;
+    }
+
+    let mut query = String::new();
+    if let Err(e) = stdin.read_to_string(&mut query) {
+        eprintln!("Failed to read query from stdin: {:#?}", e);
+        return None;
+    }
+
+    Some(query.trim().to_string())
+}
+
+// The binary index, when there is one that's valid and not older than
+// the JSON index (an `index` run without `--index-format binary` leaves a
+// stale one behind).
+fn detect_binary_index() -> Option<MmapIndex> {
+    let binary_version = Index::disk_version(BINARY_INDEX_PATH).ok()?;
+    if let Ok(json_version) = Index::disk_version(INDEX_PATH)
+        && json_version > binary_version
+    {
+        return None;
+    }
+
+    Index::open_mmap(BINARY_INDEX_PATH).ok()
+}
+
+// Whichever loaded index a search runs against
+enum SearchTarget<'a> {
+    Full(&'a Index),
+    Binary(&'a MmapIndex),
+}
+
+impl SearchTarget<'_> {
+    fn search(&self, query: &str, mode: SearchMode) -> Vec<DocumentId> {
+        match (self, mode) {
+            (SearchTarget::Full(index), SearchMode::Or) => index.search_query(query),
+            (SearchTarget::Full(index), SearchMode::And) => index.search_all(query),
+            (SearchTarget::Binary(view), SearchMode::Or) => view.search_query(query),
+            (SearchTarget::Binary(view), SearchMode::And) => view.view().search_all(query),
+        }
+    }
+
+    fn tokenize(&self, query: &str) -> Vec<String> {
+        match self {
+            SearchTarget::Full(index) => tokenize_with(query, index.tokenizer_options()),
+            // The binary layout doesn't keep tokenizer settings, it's always
+            // written with the defaults
+            SearchTarget::Binary(_) => tokenize(query),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            SearchTarget::Full(index) => index.is_empty(),
+            SearchTarget::Binary(view) => view.document_count() == 0,
         }
     }
 }
 
-fn run_search(query: String, shared_index: Arc<Mutex<Index>>) {
-    let tokens = tokenize(&query);
+fn run_search(query: String, count: bool, mode: SearchMode, target: SearchTarget) {
+    // Just the number, so scripts can capture it with $(...)
+    if count {
+        println!("{}", target.search(&query, mode).len());
+        return;
+    }
+
+    let tokens = target.tokenize(&query);
     println!("tokens from query ={:#?}", tokens);
 
-    // Lock index for reading
-    let index = shared_index.lock().unwrap();
+    // "!!! ..." isn't a query that matched nothing, it's no query at all
+    if tokens.is_empty() {
+        println!("Query contained no searchable terms");
+        return;
+    }
 
-    let results = index.search_query(&query);
+    if target.is_empty() {
+        println!("Index is empty, run `index` first");
+        return;
+    }
+
+    let results = target.search(&query, mode);
     println!("Found {} results", results.len());
 }
 
-fn create_watcher_channel(shared_index: Arc<Mutex<Index>>, shutdown_rx: Receiver<()>) {
-    let (tx, rx) = std::sync::mpsc::channel::<IndexEvent>();
+fn run_index(path: &Path, format: IndexFormat, shared_index: Arc<Mutex<Index>>) {
+    // One file or a whole directory of them
+    let docs = if path.is_dir() {
+        ingestion::load_documents(path)
+    } else {
+        ingestion::load_document(path).map(|doc| vec![doc])
+    };
 
-    let index_clone = Arc::clone(&shared_index);
+    let docs = match docs {
+        Ok(docs) => docs,
+        Err(e) => {
+            eprintln!("Failed to load {:?}: {}", path, e);
+            return;
+        }
+    };
 
-    // rx gets moved to be owned by the thread...fyi
-    std::thread::spawn(move || {
-        if let Err(e) = watcher::watch_notes(tx) {
-            eprintln!("Watcher error: {:?}", e);
+    let mut index = shared_index.lock().unwrap();
+    let count = docs.len();
+    for doc in docs {
+        index.upsert_document(doc);
+    }
+
+    if let Err(e) = index.save_to_disk(INDEX_PATH) {
+        eprintln!("Failed to persist index to disk: {:#?}", e);
+        return;
+    }
+
+    if format == IndexFormat::Binary
+        && let Err(e) = index.save_mmap(BINARY_INDEX_PATH)
+    {
+        eprintln!("Failed to write binary index: {:#?}", e);
+        return;
+    }
+
+    println!("Indexed {} documents", count);
+}
+
+fn run_index_stdin(shared_index: Arc<Mutex<Index>>) {
+    let mut text = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut text) {
+        eprintln!("Failed to read stdin: {:#?}", e);
+        return;
+    }
+
+    let text = ingestion::normalize_content(&text);
+
+    let mut index = shared_index.lock().unwrap();
+    let doc_id = index.add_text_from(&text, DocumentSource::Stdin);
+
+    if let Err(e) = index.save_to_disk(INDEX_PATH) {
+        eprintln!("Failed to persist index to disk: {:#?}", e);
+        return;
+    }
+
+    println!("Indexed stdin as document {}", doc_id);
+}
+
+fn run_export(output: Option<PathBuf>, shared_index: Arc<Mutex<Index>>) {
+    let index = shared_index.lock().unwrap();
+
+    let result = match output {
+        Some(path) => fs::File::create(&path)
+            .and_then(|file| index.export_documents_ndjson(io::BufWriter::new(file))),
+        None => index.export_documents_ndjson(io::stdout().lock()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to export documents: {:#?}", e);
+    }
+}
+
+fn run_tokens(file: &Path, counts: bool, shared_index: Arc<Mutex<Index>>) {
+    let content = match ingestion::read_content(file) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Failed to read file {:?}: {:#?}", file, e);
+            return;
         }
+    };
 
-        loop {
-            // ----------------------------------------
-            // Check for shutdown signal before doing anything
-            // ----------------------------------------
-            match shutdown_rx.try_recv() {
-                Ok(_) => {
-                    println!("Shutdown signal received, exiting watcher thread.");
-                    return; // exit the thread cleanly
-                }
-                Err(_) => {
-                    // No signal, continue processing
-                }
-            }
+    // Use the index's tokenizer settings so this matches what got indexed
+    let options = shared_index.lock().unwrap().tokenizer_options().clone();
+    let tokens = tokenize_with(&content, &options);
 
-            // ----------------------------------------
-            // Wait for a filesystem event
-            // ----------------------------------------
-            let event = match rx.recv() {
-                Ok(ev) => ev,
-                Err(_) => {
-                    println!("Watcher channel closed, exiting watcher thread.");
-                    break;
-                }
-            };
+    if !counts {
+        for token in tokens {
+            println!("{}", token);
+        }
+        return;
+    }
 
-            // ----------------------------------------
-            // Handle file reading outside of lock
-            //    - Since Reading a file doesn't require access to shared Index
-            // ----------------------------------------
-            let doc_opt = match event {
-                IndexEvent::Created(ref path) | IndexEvent::Modified(ref path) => {
-                    match fs::read_to_string(path) {
-                        Ok(contents) => Some((path.clone(), contents, SystemTime::now())),
-                        Err(e) => {
-                            eprintln!("Failed to read file {:?}: {:#?}", path, e);
-                            None
-                        }
-                    }
-                }
-                IndexEvent::Deleted(_) => None, // deletion does not need file contents
-            };
+    let mut frequencies: HashMap<String, usize> = HashMap::new();
+    for token in tokens {
+        *frequencies.entry(token).or_default() += 1;
+    }
 
-            // ----------------------------------------
-            // ock the index ONLY when we need to mutate it
-            // ----------------------------------------
-            {
-                let mut index = index_clone.lock().unwrap(); // lock begins
-
-                match event {
-                    IndexEvent::Created(_) | IndexEvent::Modified(_) => {
-                        if let Some((path, contents, timestamp)) = doc_opt {
-                            // Check if the document already exists
-                            let doc_id = if let Some(existing_id) = index.path_to_id.get(&path) {
-                                *existing_id
-                            } else {
-                                Uuid::new_v4()
-                            };
-
-                            // Build the Document struct
-                            let doc = Document {
-                                id: doc_id,
-                                path,
-                                content: contents,
-                                modified: Some(timestamp),
-                            };
-
-                            // Insert or update the document in the index
-                            index.upsert_document(doc);
-                        }
-                    }
-                    IndexEvent::Deleted(ref path) => {
-                        // Remove document by path
-                        index.remove_document_by_path(path);
-                    }
-                }
-            } // lock ends here
+    // Most frequent first, alphabetical within a count
+    let mut frequencies: Vec<(String, usize)> = frequencies.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    for (token, count) in frequencies {
+        println!("{} {}", token, count);
+    }
+}
+
+// Progress goes to stderr so it never mixes with output scripts parse
+fn reconcile_notes(binary: bool, shared_index: &Arc<Mutex<Index>>) {
+    let mut index = shared_index.lock().unwrap();
 
-            // ----------------------------------------
-            // Save the index to disk
-            //    - We take a brief lock to get the snapshot, then release
-            // ----------------------------------------
-            if let Err(e) = shared_index.lock().unwrap().save_to_disk(INDEX_PATH) {
+    match index.reconcile(Path::new(watcher::NOTES_DIR)) {
+        Ok(report) if report != ReconcileReport::default() => {
+            eprintln!(
+                "Reconciled notes: {} added, {} updated, {} removed",
+                report.added, report.updated, report.removed
+            );
+
+            if let Err(e) = index.save_to_disk(INDEX_PATH) {
                 eprintln!("Failed to persist index to disk: {:#?}", e);
+            } else if binary && let Err(e) = index.save_mmap(BINARY_INDEX_PATH) {
+                eprintln!("Failed to write binary index: {:#?}", e);
             }
         }
+        Ok(_) => {}
+        // No notes folder yet, nothing to catch up on
+        Err(IngestError::NotDirectory) => {}
+        Err(e) => eprintln!("Failed to reconcile notes: {:#?}", e),
+    }
+}
+
+fn run_watch(binary: bool, shared_index: Arc<Mutex<Index>>) {
+    // 1. Catch up on anything that changed while we weren't running, before
+    //    any live event can touch the index
+    reconcile_notes(binary, &shared_index);
+
+    // 2. The watcher blocks for good, so it gets its own thread
+    let (tx, rx) = std::sync::mpsc::channel::<IndexEvent>();
+    std::thread::spawn(move || {
+        if let Err(e) = watcher::watch_notes(tx) {
+            eprintln!("Watcher error: {:?}", e);
+        }
     });
+
+    // 3. Apply each batch of events, then save the index to disk
+    //    - Editors fire Create+Modify bursts, one upsert is enough
+    let binary_path = binary.then(|| Path::new(BINARY_INDEX_PATH));
+    while let Some(events) = watcher::recv_coalesced(&rx, watcher::DEBOUNCE_WINDOW) {
+        watcher::apply_events(
+            &shared_index,
+            events,
+            Path::new(INDEX_PATH),
+            binary_path,
+            None,
+        );
+    }
+
+    eprintln!("Watcher stopped");
 }
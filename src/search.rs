@@ -1,4 +1,4 @@
 // placeholder so module exists while we build the skeleton
 pub fn init() {
     // later: initialize index structure here
-}
\ No newline at end of file
+}
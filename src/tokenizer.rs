@@ -1,22 +1,284 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::canonical_combining_class;
+
+/// What to do with apostrophes inside words like "don't".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApostropheMode {
+    /// Treat them as punctuation: "don't" -> ["don", "t"]
+    #[default]
+    Split,
+    /// Drop them: "don't" -> ["dont"]
+    Strip,
+    /// Keep contractions whole: "don't" -> ["don't"]. Quotes around a word
+    /// ('like this') are still removed.
+    Keep,
+}
+
+/// Knobs for `tokenize_with`. `Default` matches plain `tokenize`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenizerOptions {
+    #[serde(default)]
+    pub apostrophe: ApostropheMode,
+    /// Treat `_` as part of a word so snake_case identifiers like
+    /// `read_to_string` stay one token. Off by default (they're split).
+    #[serde(default)]
+    pub keep_underscores: bool,
+    /// Treat hyphens inside a word as part of it, so "state-of-the-art"
+    /// stays one token. Leading, trailing and doubled (`--`) hyphens still
+    /// separate words. Off by default (compounds are split).
+    #[serde(default)]
+    pub keep_hyphens: bool,
+    /// Drop tokens longer than this many characters, e.g. the single
+    /// megabyte-long "word" of a base64 blob. They're never useful search
+    /// terms and only bloat the index. `None` (the default) keeps everything.
+    #[serde(default)]
+    pub max_token_chars: Option<usize>,
+    /// Apply Unicode NFC before splitting, so "é" typed as one code point
+    /// and as "e" + a combining accent give the same tokens. Accents are
+    /// kept (this isn't diacritic folding), only their encoding is unified.
+    /// Off by default.
+    #[serde(default)]
+    pub normalize_nfc: bool,
+}
+
+/// How a document's text is split into tokens, chosen per language.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TokenStrategy {
+    /// Words only, exactly what `tokenize_with` produces.
+    #[default]
+    Words,
+    /// Words, plus overlapping character n-grams of this size over runs of
+    /// non-ASCII letters. Scripts like Chinese or Japanese don't put spaces
+    /// between words (and word tokenization drops them), so "日本語" is
+    /// indexed as "日本", "本語" with `NGrams(2)`.
+    NGrams(usize),
+}
+
 pub fn tokenize(text: &str) -> Vec<String> {
-    // 1. convert text to lower case
-    let lower = text.to_lowercase();
+    tokenize_with(text, &TokenizerOptions::default())
+}
+
+pub fn tokenize_with(text: &str, options: &TokenizerOptions) -> Vec<String> {
+    tokenize_spans(text, options)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect()
+}
 
-    // 2. create a new empty String buffer
-    let mut cleaned = String::new();
+/// `tokenize_with`, plus the byte range in `text` each token came from.
+///
+/// Ranges always fall on char boundaries of the original `text`, so
+/// `&text[range]` is safe even next to multi-byte characters. A range covers
+/// the token's first to last word character, including anything the mode
+/// dropped in between (e.g. the apostrophe of "don't" under `Strip`). With
+/// `normalize_nfc` ranges still point into the original, unnormalized text.
+pub fn tokenize_spans(text: &str, options: &TokenizerOptions) -> Vec<(String, Range<usize>)> {
+    let mut spans = Vec::new();
+    let mut token = String::new();
+    let mut range: Option<Range<usize>> = None;
+    // Set once the pending token outgrows `max_token_chars`; from then on
+    // it's no longer built up, just skipped until it ends
+    let mut oversized = false;
+    let max_chars = options.max_token_chars.unwrap_or(usize::MAX);
 
-    for ch in lower.chars() {
-        // 3. if char is ASCII alphanumeric or whitespace the push char
-        if ch.is_ascii_alphanumeric() || ch.is_whitespace() {
-            cleaned.push(ch);
+    for (at, unit) in char_units(text, options.normalize_nfc) {
+        // 1. Lowercase char by char so every output char maps back to the
+        //    source char (or combining sequence) it came from
+        let end = at + unit.len();
+        let unit = if options.normalize_nfc && !unicode_normalization::is_nfc(unit) {
+            Cow::Owned(unit.nfc().collect())
         } else {
-            // 4. Otherwise replace punctuation with white space
-            cleaned.push(' '); // replace punctuation or non ascii chars
+            Cow::Borrowed(unit)
+        };
+
+        for lower in unit.chars().flat_map(char::to_lowercase) {
+            // 2. ASCII alphanumerics (and underscores, if kept) are word chars
+            if lower.is_ascii_alphanumeric() || (lower == '_' && options.keep_underscores) {
+                // Token chars are all ASCII, so bytes == chars
+                if oversized || token.len() >= max_chars {
+                    oversized = true;
+                    continue;
+                }
+
+                token.push(lower);
+                range = Some(range.map_or(at..end, |r| r.start..end));
+            } else if lower == '-' && options.keep_hyphens {
+                // 3. A hyphen joins words; a second one in a row ends it
+                if token.ends_with('-') {
+                    flush(&mut token, &mut range, &mut oversized, &mut spans);
+                } else if !token.is_empty() && !oversized {
+                    token.push('-');
+                }
+            } else if is_apostrophe(lower) {
+                // 4. Apostrophes depend on the configured mode
+                match options.apostrophe {
+                    ApostropheMode::Split => {
+                        flush(&mut token, &mut range, &mut oversized, &mut spans)
+                    }
+                    ApostropheMode::Strip => {}
+                    ApostropheMode::Keep if !oversized => token.push('\''),
+                    ApostropheMode::Keep => {}
+                }
+            } else {
+                // 5. Whitespace, punctuation and non-ASCII chars end a token
+                flush(&mut token, &mut range, &mut oversized, &mut spans);
+            }
+        }
+    }
+    flush(&mut token, &mut range, &mut oversized, &mut spans);
+
+    spans
+}
+
+// Splits `text` into the pieces `tokenize_spans` handles one at a time,
+// with their byte offsets: single chars, or when composing, a char plus the
+// combining marks after it, since NFC can only merge those with each other.
+fn char_units(text: &str, compose: bool) -> impl Iterator<Item = (usize, &str)> {
+    let mut chars = text.char_indices().peekable();
+
+    std::iter::from_fn(move || {
+        let (start, ch) = chars.next()?;
+        let mut end = start + ch.len_utf8();
+
+        while compose
+            && let Some(&(at, next)) = chars.peek()
+            && canonical_combining_class(next) != 0
+        {
+            end = at + next.len_utf8();
+            chars.next();
         }
+
+        Some((start, &text[start..end]))
+    })
+}
+
+// Emits the pending token, dropping apostrophes that were really quotes and
+// hyphens that didn't join anything. An oversized token is dropped whole.
+fn flush(
+    token: &mut String,
+    range: &mut Option<Range<usize>>,
+    oversized: &mut bool,
+    spans: &mut Vec<(String, Range<usize>)>,
+) {
+    let trimmed = token.trim_matches(|ch| ch == '\'' || ch == '-');
+    let oversized = std::mem::take(oversized);
+
+    if let Some(range) = range.take()
+        && !trimmed.is_empty()
+        && !oversized
+    {
+        spans.push((trimmed.to_string(), range));
     }
 
-    // split into tokens
-    cleaned.split_whitespace().map(|s| s.to_string()).collect()
+    token.clear();
+}
+
+/// Character n-grams of every run of non-ASCII letters/digits in `text`,
+/// lowercased. Runs shorter than `n` are emitted whole. ASCII text is left
+/// to `tokenize_with`, so this returns nothing for plain English.
+pub fn tokenize_ngrams(text: &str, n: usize) -> Vec<String> {
+    let n = n.max(1);
+    let mut grams = Vec::new();
+
+    let runs = text.split(|ch: char| ch.is_ascii() || !ch.is_alphanumeric());
+    for run in runs.filter(|run| !run.is_empty()) {
+        let chars: Vec<char> = run.to_lowercase().chars().collect();
+
+        if chars.len() <= n {
+            grams.push(chars.into_iter().collect());
+            continue;
+        }
+
+        grams.extend(chars.windows(n).map(|gram| gram.iter().collect::<String>()));
+    }
+
+    grams
+}
+
+// Straight and typographic (U+2019) apostrophes
+fn is_apostrophe(ch: char) -> bool {
+    matches!(ch, '\'' | '\u{2019}')
+}
+
+/// Incremental `tokenize` for text that arrives in byte chunks.
+///
+/// Produces exactly the tokens `tokenize` would for the concatenated input.
+/// Only text up to the last whitespace of each chunk is tokenized, the rest
+/// (a possibly unfinished word, or half of a multi-byte char) is carried
+/// into the next `feed`.
+#[derive(Debug, Default)]
+pub struct StreamTokenizer {
+    options: TokenizerOptions,
+    // Undecoded bytes from the end of the previous chunk (split UTF-8 char)
+    pending_bytes: Vec<u8>,
+    // Decoded text after the last whitespace we've seen
+    pending_text: String,
+}
+
+impl StreamTokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Streams with the same options `tokenize_with` would use.
+    pub fn with_options(options: TokenizerOptions) -> Self {
+        StreamTokenizer {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Feeds the next chunk, calling `emit` for every token it completes.
+    pub fn feed<F: FnMut(String)>(&mut self, bytes: &[u8], mut emit: F) {
+        // 1. Decode as much UTF-8 as possible, carrying a split char forward
+        self.pending_bytes.extend_from_slice(bytes);
+        let decoded_len = match std::str::from_utf8(&self.pending_bytes) {
+            Ok(text) => text.len(),
+            // Incomplete sequence at the end: keep it for the next chunk
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            // Genuinely invalid bytes: decode lossily, they become separators
+            Err(_) => self.pending_bytes.len(),
+        };
+
+        let rest = self.pending_bytes.split_off(decoded_len);
+        let decoded = std::mem::replace(&mut self.pending_bytes, rest);
+        let searched = self.pending_text.len();
+        self.pending_text
+            .push_str(&String::from_utf8_lossy(&decoded));
+
+        // 2. Everything before the last whitespace can't change any more.
+        //    Older text had none (or it'd have been flushed), so only the new
+        //    part is searched; a huge word spread over many chunks stays linear.
+        let Some((split, ws)) = self.pending_text[searched..]
+            .char_indices()
+            .rev()
+            .find(|(_, ch)| ch.is_whitespace())
+        else {
+            return;
+        };
+        let split = searched + split;
+
+        let tail = self.pending_text.split_off(split + ws.len_utf8());
+        for token in tokenize_with(&self.pending_text, &self.options) {
+            emit(token);
+        }
+        self.pending_text = tail;
+    }
+
+    /// Flushes whatever is still buffered once the input is exhausted.
+    pub fn finish<F: FnMut(String)>(mut self, mut emit: F) {
+        if !self.pending_bytes.is_empty() {
+            self.pending_text
+                .push_str(&String::from_utf8_lossy(&self.pending_bytes));
+        }
+
+        for token in tokenize_with(&self.pending_text, &self.options) {
+            emit(token);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -47,4 +309,216 @@ mod tests {
         let tokens = tokenize("naïve café");
         assert_eq!(tokens, vec!["na", "ve", "caf"]);
     }
+
+    #[test]
+    fn test_stream_tokenizer_matches_tokenize() {
+        let text = "Streamed   café text, split-up words\nand more";
+
+        for step in 1..text.len() {
+            let mut stream = StreamTokenizer::new();
+            let mut tokens = Vec::new();
+
+            for chunk in text.as_bytes().chunks(step) {
+                stream.feed(chunk, |t| tokens.push(t));
+            }
+            stream.finish(|t| tokens.push(t));
+
+            assert_eq!(tokens, tokenize(text), "chunk size {}", step);
+        }
+    }
+
+    fn tokenize_mode(text: &str, apostrophe: ApostropheMode) -> Vec<String> {
+        tokenize_with(
+            text,
+            &TokenizerOptions {
+                apostrophe,
+                ..TokenizerOptions::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_apostrophe_split_is_default() {
+        assert_eq!(tokenize("don't"), vec!["don", "t"]);
+        assert_eq!(
+            tokenize_mode("It's", ApostropheMode::Split),
+            vec!["it", "s"]
+        );
+        assert_eq!(
+            tokenize_mode("rock'n'roll", ApostropheMode::Split),
+            vec!["rock", "n", "roll"]
+        );
+    }
+
+    #[test]
+    fn test_apostrophe_strip() {
+        assert_eq!(tokenize_mode("don't", ApostropheMode::Strip), vec!["dont"]);
+        assert_eq!(
+            tokenize_mode("It\u{2019}s", ApostropheMode::Strip),
+            vec!["its"]
+        );
+        assert_eq!(
+            tokenize_mode("rock'n'roll", ApostropheMode::Strip),
+            vec!["rocknroll"]
+        );
+    }
+
+    #[test]
+    fn test_apostrophe_keep() {
+        assert_eq!(tokenize_mode("don't", ApostropheMode::Keep), vec!["don't"]);
+        // Typographic apostrophes are normalized to the ASCII one
+        assert_eq!(
+            tokenize_mode("It\u{2019}s", ApostropheMode::Keep),
+            vec!["it's"]
+        );
+        assert_eq!(
+            tokenize_mode("rock'n'roll", ApostropheMode::Keep),
+            vec!["rock'n'roll"]
+        );
+
+        // Quote marks around words aren't part of the word
+        assert_eq!(
+            tokenize_mode("'quoted' words'", ApostropheMode::Keep),
+            vec!["quoted", "words"]
+        );
+    }
+
+    #[test]
+    fn test_underscores_split_by_default() {
+        assert_eq!(tokenize("read_to_string"), vec!["read", "to", "string"]);
+    }
+
+    #[test]
+    fn test_keep_underscores() {
+        let options = TokenizerOptions {
+            keep_underscores: true,
+            ..TokenizerOptions::default()
+        };
+
+        assert_eq!(
+            tokenize_with("Call fs::read_to_string(path)", &options),
+            vec!["call", "fs", "read_to_string", "path"]
+        );
+    }
+
+    #[test]
+    fn test_keep_hyphens() {
+        let options = TokenizerOptions {
+            keep_hyphens: true,
+            ..TokenizerOptions::default()
+        };
+
+        assert_eq!(
+            tokenize_with("State-of-the-art and well-known", &options),
+            vec!["state-of-the-art", "and", "well-known"]
+        );
+
+        // Dangling and doubled hyphens don't join anything
+        assert_eq!(
+            tokenize_with("-foo bar- baz--qux - x", &options),
+            vec!["foo", "bar", "baz", "qux", "x"]
+        );
+
+        // Off by default
+        assert_eq!(tokenize("well-known"), vec!["well", "known"]);
+
+        // The span of a dangling hyphen's word is just the word
+        let text = "-foo";
+        let spans = tokenize_spans(text, &options);
+        assert_eq!(&text[spans[0].1.clone()], "foo");
+    }
+
+    #[test]
+    fn test_normalize_nfc_unifies_composed_and_decomposed_forms() {
+        let options = TokenizerOptions {
+            normalize_nfc: true,
+            ..TokenizerOptions::default()
+        };
+        let precomposed = "Caf\u{e9} au lait";
+        let decomposed = "Cafe\u{301} au lait";
+
+        assert_eq!(
+            tokenize_with(precomposed, &options),
+            tokenize_with(decomposed, &options)
+        );
+        assert_eq!(
+            tokenize_with(decomposed, &options),
+            vec!["caf", "au", "lait"]
+        );
+
+        // Without it the bare "e" of the decomposed form sticks to the word
+        assert_eq!(tokenize(decomposed)[0], "cafe");
+
+        // Spans still index into the original text
+        let text = "e\u{301}t\u{e9} cafe\u{301}s";
+        let spans = tokenize_spans(text, &options);
+        let words: Vec<&str> = spans
+            .iter()
+            .map(|(_, range)| &text[range.clone()])
+            .collect();
+        assert_eq!(words, vec!["t", "caf", "s"]);
+    }
+
+    #[test]
+    fn test_max_token_chars_drops_oversized_tokens() {
+        let options = TokenizerOptions {
+            max_token_chars: Some(8),
+            ..TokenizerOptions::default()
+        };
+        let blob = "QUJD".repeat(250_000);
+        let text = format!("before {} after eightchr ninechars", blob);
+
+        assert_eq!(
+            tokenize_with(&text, &options),
+            vec!["before", "after", "eightchr"]
+        );
+
+        // Spans of the surviving tokens are unaffected
+        let spans = tokenize_spans(&text, &options);
+        assert_eq!(&text[spans[1].1.clone()], "after");
+
+        // Unset keeps everything, like before
+        assert_eq!(tokenize(&text).len(), 5);
+    }
+
+    #[test]
+    fn test_spans_point_back_into_the_original_text() {
+        let text = "Café RUST, naïve—über don't";
+        let spans = tokenize_spans(text, &TokenizerOptions::default());
+
+        let tokens: Vec<&str> = spans.iter().map(|(token, _)| token.as_str()).collect();
+        assert_eq!(tokens, tokenize(text));
+        assert_eq!(tokens, vec!["caf", "rust", "na", "ve", "ber", "don", "t"]);
+
+        let sources: Vec<&str> = spans
+            .iter()
+            .map(|(_, range)| &text[range.clone()])
+            .collect();
+        assert_eq!(sources, vec!["Caf", "RUST", "na", "ve", "ber", "don", "t"]);
+    }
+
+    #[test]
+    fn test_spans_cover_stripped_apostrophes() {
+        let text = "'quoted' don't";
+        let options = TokenizerOptions {
+            apostrophe: ApostropheMode::Strip,
+            ..TokenizerOptions::default()
+        };
+
+        let spans = tokenize_spans(text, &options);
+        assert_eq!(
+            spans,
+            vec![("quoted".to_string(), 1..7), ("dont".to_string(), 9..14)]
+        );
+    }
+
+    #[test]
+    fn test_ngrams_cover_non_ascii_runs_only() {
+        assert_eq!(
+            tokenize_ngrams("日本語 rust 東京", 2),
+            vec!["日本", "本語", "東京"]
+        );
+        assert_eq!(tokenize_ngrams("語", 2), vec!["語"]);
+        assert!(tokenize_ngrams("plain english", 2).is_empty());
+    }
 }
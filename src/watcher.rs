@@ -1,23 +1,346 @@
+use crate::index::Index;
+use crate::ingestion::{
+    DEFAULT_EXTENSIONS, Document, IgnoreRules, has_allowed_extension, read_content,
+};
+use log::{error, info, warn};
 use notify::{Event, RecursiveMode, Result as NotifyResult, Watcher};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::Sender;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum IndexEvent {
     Created(PathBuf),
     Modified(PathBuf),
     Deleted(PathBuf),
 }
 
+/// Progress reported by `apply_events`, separate from the filesystem events
+/// themselves (e.g. for a status line, or tests waiting on the indexer).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexStatus {
+    /// The index was written to disk, holding this many documents.
+    Saved { documents: usize },
+}
+
+/// Directory watched by `watch_notes`.
+pub const NOTES_DIR: &str = "./notes";
+
+// How often the supervisor checks that the watch root still exists
+const ROOT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long `recv_coalesced` keeps collecting after the first event.
+pub const DEBOUNCE_WINDOW: Duration = Duration::from_millis(100);
+
+/// Knobs for `watch_dir`. `Default` matches `watch_notes`.
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Send a `Created` event for every matching file already in the tree
+    /// when the watch starts, so one event stream covers both the initial
+    /// indexing and live updates.
+    pub emit_existing: bool,
+    /// Extensions (without the dot) to watch, compared case-insensitively
+    /// like `LoadOptions::extensions`. `.md`/`.txt` by default.
+    pub extensions: Vec<String>,
+    /// Drop events for paths matched by the root's `.gitignore` or
+    /// `.searchignore` (see `IgnoreRules`), and don't emit them for
+    /// `emit_existing` either. The files are re-read whenever a root starts
+    /// being watched. On by default, like `LoadOptions::respect_ignore_files`,
+    /// so live updates skip exactly what initial ingestion skipped.
+    pub respect_ignore_files: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            emit_existing: false,
+            extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            respect_ignore_files: true,
+        }
+    }
+}
+
+// Ignore rules of every watched root, shared with the event callback
+type IgnoreSet = Arc<RwLock<Vec<IgnoreRules>>>;
+
 // Listen to filesystem events and publish IndexEvents.
 pub fn watch_notes(tx: Sender<IndexEvent>) -> NotifyResult<()> {
+    watch_dir(Path::new(NOTES_DIR), tx, &WatchOptions::default())
+}
+
+/// Watches `root` recursively for the lifetime of the program.
+///
+/// If `root` is deleted the OS-level watch goes stale, so a small supervisor
+/// loop polls the directory and re-registers the watch once it reappears
+/// (or is replaced by a new directory with the same name).
+pub fn watch_dir(root: &Path, tx: Sender<IndexEvent>, options: &WatchOptions) -> NotifyResult<()> {
     // 1. Create a filesystem watcher with a callback
-    let mut watcher = notify::recommended_watcher(move |res| {
-        // 2. Handle notify-level errors defensively
+    let ignore = IgnoreSet::default();
+    let mut watcher = notify::recommended_watcher(event_handler(
+        tx.clone(),
+        ignore.clone(),
+        options.extensions.clone(),
+    ))?;
+
+    // 2. Keep the watch registered for the lifetime of the program
+    let mut root_watch = RootWatch::new(root);
+    loop {
+        let was_watching = root_watch.is_watching();
+        let watching = root_watch.refresh(&mut watcher)?;
+
+        if watching && !was_watching && options.respect_ignore_files {
+            set_ignore_rules(&ignore, load_ignore_rules(root));
+        }
+
+        // 3. Watch is (re)registered first so nothing slips through between
+        //    the snapshot and live events; duplicates are harmless upserts.
+        if watching && !was_watching && options.emit_existing {
+            emit_existing_filtered(
+                root,
+                &tx,
+                &ignore_rules_for(&ignore, root),
+                &options.extensions,
+            )?;
+        }
+
+        std::thread::sleep(ROOT_POLL_INTERVAL);
+    }
+}
+
+/// Like `watch_dir`, but for every directory matching a glob such as
+/// `projects/*/notes`.
+///
+/// The pattern is re-expanded on every supervisor tick, so directories that
+/// start matching later are picked up too.
+pub fn watch_glob(
+    pattern: &str,
+    tx: Sender<IndexEvent>,
+    options: &WatchOptions,
+) -> NotifyResult<()> {
+    let mut glob_watch =
+        GlobWatch::new(pattern).map_err(|e| notify::Error::generic(&e.to_string()))?;
+    let ignore = IgnoreSet::default();
+    let mut watcher = notify::recommended_watcher(event_handler(
+        tx.clone(),
+        ignore.clone(),
+        options.extensions.clone(),
+    ))?;
+
+    loop {
+        for root in glob_watch.refresh(&mut watcher)? {
+            if options.respect_ignore_files {
+                set_ignore_rules(&ignore, load_ignore_rules(&root));
+            }
+            if options.emit_existing {
+                emit_existing_filtered(
+                    &root,
+                    &tx,
+                    &ignore_rules_for(&ignore, &root),
+                    &options.extensions,
+                )?;
+            }
+        }
+
+        std::thread::sleep(ROOT_POLL_INTERVAL);
+    }
+}
+
+/// Walks `root` recursively and sends a `Created` event for every `.txt`/`.md`
+/// file, returning how many were sent. Stops early if the receiver is gone.
+pub fn emit_existing_files(root: &Path, tx: &Sender<IndexEvent>) -> std::io::Result<usize> {
+    emit_existing_filtered(root, tx, &IgnoreRules::parse(root, ""), DEFAULT_EXTENSIONS)
+}
+
+// `emit_existing_files` for the given extensions, skipping ignored files and
+// whole ignored directories
+fn emit_existing_filtered<S: AsRef<str>>(
+    root: &Path,
+    tx: &Sender<IndexEvent>,
+    ignore: &IgnoreRules,
+    extensions: &[S],
+) -> std::io::Result<usize> {
+    let mut sent = 0;
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+
+            if ignore.is_ignored(&path) {
+                continue;
+            }
+
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            if !file_type.is_file() || !has_allowed_extension(&path, extensions) {
+                continue;
+            }
+
+            if tx.send(IndexEvent::Created(path)).is_err() {
+                return Ok(sent);
+            }
+            sent += 1;
+        }
+    }
+
+    Ok(sent)
+}
+
+/// Blocks for the next event, then collects whatever else arrives within
+/// `window` and returns the batch run through `coalesce_events`.
+///
+/// Returns `None` once every sender is gone and nothing is left to read.
+pub fn recv_coalesced(rx: &Receiver<IndexEvent>, window: Duration) -> Option<Vec<IndexEvent>> {
+    // 1. Wait as long as it takes for the first event
+    let mut batch = vec![rx.recv().ok()?];
+
+    // 2. Then only until the window closes
+    let deadline = Instant::now() + window;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok(event) => batch.push(event),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Some(coalesce_events(batch))
+}
+
+/// Collapses repeated writes to the same path into one event.
+///
+/// Editors often create a file and immediately modify it (or save it several
+/// times in a row), which would otherwise mean several upserts of the same
+/// file. Any `Created`/`Modified` for a path that already has one pending is
+/// dropped, keeping the first one's kind and position. A `Deleted` in between
+/// breaks the run, so delete-then-recreate still comes through in order.
+pub fn coalesce_events(events: Vec<IndexEvent>) -> Vec<IndexEvent> {
+    let mut pending_writes: HashSet<PathBuf> = HashSet::new();
+    let mut coalesced = Vec::with_capacity(events.len());
+
+    for event in events {
+        match &event {
+            IndexEvent::Created(path) | IndexEvent::Modified(path) => {
+                if !pending_writes.insert(path.clone()) {
+                    continue;
+                }
+            }
+            IndexEvent::Deleted(path) => {
+                pending_writes.remove(path);
+            }
+        }
+
+        coalesced.push(event);
+    }
+
+    coalesced
+}
+
+/// Applies a batch of events (see `recv_coalesced`) to the shared index,
+/// then saves it to `save_path`, sending `IndexStatus::Saved` on `status`
+/// once the save has hit disk. With a `binary_path` the memory-mapped search
+/// layout (`Index::save_mmap`) is rewritten there too, so it never goes
+/// stale next to the JSON.
+///
+/// Files are read before taking the lock, so searches aren't blocked on
+/// disk I/O. Unreadable files are logged and skipped, as is a failed save
+/// (no status is sent then).
+pub fn apply_events(
+    shared_index: &Mutex<Index>,
+    events: Vec<IndexEvent>,
+    save_path: &Path,
+    binary_path: Option<&Path>,
+    status: Option<&Sender<IndexStatus>>,
+) {
+    for event in events {
+        // ----------------------------------------
+        // Handle file reading outside of lock
+        //    - Since Reading a file doesn't require access to shared Index
+        // ----------------------------------------
+        let doc_opt = match event {
+            IndexEvent::Created(ref path) | IndexEvent::Modified(ref path) => {
+                match read_content(path) {
+                    Ok(contents) => Some((path.clone(), contents, SystemTime::now())),
+                    Err(e) => {
+                        warn!("failed to read file {:?}: {}", path, e);
+                        None
+                    }
+                }
+            }
+            IndexEvent::Deleted(_) => None, // deletion does not need file contents
+        };
+
+        // ----------------------------------------
+        // Lock the index ONLY when we need to mutate it
+        // ----------------------------------------
+        let mut index = shared_index.lock().unwrap(); // lock begins
+
+        match event {
+            IndexEvent::Created(_) | IndexEvent::Modified(_) => {
+                if let Some((path, contents, timestamp)) = doc_opt {
+                    // Keep the id if the document already exists
+                    let mut doc = Document::file(path, contents, Some(timestamp));
+                    if let Some(id) = index.id_for_path(&doc.path) {
+                        doc.id = id;
+                    }
+
+                    // Insert or update the document in the index
+                    index.upsert_document(doc);
+                }
+            }
+            IndexEvent::Deleted(ref path) => {
+                // Remove document by path
+                index.remove_document_by_path(path);
+            }
+        }
+    } // lock ends here
+
+    // ----------------------------------------
+    // Save the index to disk
+    //    - We take a brief lock to get the snapshot, then release
+    // ----------------------------------------
+    let index = shared_index.lock().unwrap();
+    if let Err(e) = index.save_to_disk(save_path) {
+        error!("failed to persist index to disk: {}", e);
+        return;
+    }
+
+    if let Some(binary_path) = binary_path
+        && let Err(e) = index.save_mmap(binary_path)
+    {
+        error!("failed to write binary index: {}", e);
+        return;
+    }
+
+    if let Some(status) = status {
+        // Nobody listening any more is fine, the save still happened
+        let _ = status.send(IndexStatus::Saved {
+            documents: index.document_count(),
+        });
+    }
+}
+
+// Builds the notify callback that turns OS events into IndexEvents.
+// Diagnostics go through the `log` facade so embedding apps can route them.
+// Paths any of the `ignore` rules match are dropped.
+fn event_handler(
+    tx: Sender<IndexEvent>,
+    ignore: IgnoreSet,
+    extensions: Vec<String>,
+) -> impl FnMut(NotifyResult<Event>) + Send + 'static {
+    move |res| {
+        // 1. Handle notify-level errors defensively
         let event: Event = match res {
             Ok(event) => event,
             Err(e) => {
-                eprintln!("watch error: {:?}", e);
+                error!("watch error: {:?}", e);
                 return;
             }
         };
@@ -30,35 +353,184 @@ pub fn watch_notes(tx: Sender<IndexEvent>) -> NotifyResult<()> {
             _ => return, // Ignore unrelated filesystem noise
         };
 
-        // 4. Handle each affected path independently
+        // 2. Handle each affected path independently
         // Never assume 1 event = 1 path. Always iterate event.paths
         for path in event.paths {
-            // 5. Filter for only files we care about (.txt / .md)
-            if !matches!(
-                path.extension().and_then(|e| e.to_str()),
-                Some("txt" | "md")
-            ) {
+            // 3. Filter for only files we care about (.txt / .md by default),
+            //    ignoring case the same way ingestion does
+            if !has_allowed_extension(&path, &extensions) {
                 continue;
             }
 
-            // 6. Send a domain level event to the indexer
+            // 4. Skip what the ignore files exclude, same as the initial walk
+            if ignore
+                .read()
+                .is_ok_and(|sets| sets.iter().any(|rules| rules.is_ignored(&path)))
+            {
+                continue;
+            }
+
+            // 5. Send a domain level event to the indexer
             if tx.send(make_index_event(path.clone())).is_err() {
                 // Receiver is gone then just do a graceful shutdown
-                eprintln!("index receiver dropped; stopping watcher");
+                warn!("index receiver dropped; stopping watcher");
                 return;
             }
         }
-    })?;
+    }
+}
 
-    // 7. Start watching the ./notes directory recursively
-    watcher.watch(Path::new("./notes"), RecursiveMode::Recursive)?;
+// Ignore rules for `root`, or none if its ignore files can't be read.
+fn load_ignore_rules(root: &Path) -> IgnoreRules {
+    IgnoreRules::load(root).unwrap_or_else(|e| {
+        warn!("could not read ignore files in {}: {}", root.display(), e);
+        IgnoreRules::parse(root, "")
+    })
+}
 
-    // 8. Keep the watcher alive for the lifetime of the program
-    loop {
-        std::thread::park();
+// Adds `rules` to the set, replacing any earlier rules for the same root.
+fn set_ignore_rules(ignore: &IgnoreSet, rules: IgnoreRules) {
+    if let Ok(mut sets) = ignore.write() {
+        sets.retain(|existing| existing.root() != rules.root());
+        sets.push(rules);
+    }
+}
+
+fn ignore_rules_for(ignore: &IgnoreSet, root: &Path) -> IgnoreRules {
+    let rules = IgnoreRules::parse(root, "");
+
+    ignore
+        .read()
+        .ok()
+        .and_then(|sets| {
+            sets.iter()
+                .find(|existing| existing.root() == rules.root())
+                .cloned()
+        })
+        .unwrap_or(rules)
+}
+
+/// Tracks whether the watch on a root directory is live.
+///
+/// `refresh` is the supervisor's single step, split out so the
+/// vanish-and-reappear handling can be tested without the endless loop.
+#[derive(Debug)]
+pub struct RootWatch {
+    root: PathBuf,
+    // Identity of the directory we registered, None while not watching
+    watched: Option<DirIdentity>,
+}
+
+impl RootWatch {
+    pub fn new(root: &Path) -> Self {
+        RootWatch {
+            root: root.to_path_buf(),
+            watched: None,
+        }
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watched.is_some()
+    }
+
+    /// Registers, drops, or re-registers the watch to match what's on disk.
+    /// Returns whether the root is being watched afterwards.
+    pub fn refresh<W: Watcher>(&mut self, watcher: &mut W) -> NotifyResult<bool> {
+        let current = dir_identity(&self.root);
+
+        if current == self.watched {
+            return Ok(self.is_watching());
+        }
+
+        // Root vanished or was swapped out: the old watch is dead either way.
+        // notify may already have dropped it, so an unwatch error is fine.
+        if self.watched.take().is_some() {
+            let _ = watcher.unwatch(&self.root);
+            info!("stopped watching {}", self.root.display());
+        }
+
+        if current.is_some() {
+            watcher.watch(&self.root, RecursiveMode::Recursive)?;
+            self.watched = current;
+            info!("watching {}", self.root.display());
+        }
+
+        Ok(self.is_watching())
+    }
+}
+
+/// A `RootWatch` for each directory matching a glob pattern.
+#[derive(Debug)]
+pub struct GlobWatch {
+    pattern: String,
+    roots: Vec<RootWatch>,
+}
+
+impl GlobWatch {
+    /// Fails if `pattern` isn't a valid glob. Nothing is watched until the
+    /// first `refresh`.
+    pub fn new(pattern: &str) -> Result<Self, glob::PatternError> {
+        glob::Pattern::new(pattern)?;
+
+        Ok(GlobWatch {
+            pattern: pattern.to_string(),
+            roots: Vec::new(),
+        })
+    }
+
+    /// Directories currently being watched, in match order.
+    pub fn watched_roots(&self) -> Vec<&Path> {
+        self.roots
+            .iter()
+            .filter(|root| root.is_watching())
+            .map(|root| root.root.as_path())
+            .collect()
+    }
+
+    /// Expands the pattern again and refreshes every root. Returns the roots
+    /// that became watched in this step.
+    pub fn refresh<W: Watcher>(&mut self, watcher: &mut W) -> NotifyResult<Vec<PathBuf>> {
+        // 1. Track any directory that newly matches the pattern
+        let matches =
+            glob::glob(&self.pattern).map_err(|e| notify::Error::generic(&e.to_string()))?;
+        for path in matches.flatten() {
+            if path.is_dir() && !self.roots.iter().any(|root| root.root == path) {
+                self.roots.push(RootWatch::new(&path));
+            }
+        }
+
+        // 2. Refresh them all; vanished ones drop out until they reappear
+        let mut started = Vec::new();
+        for root in &mut self.roots {
+            let was_watching = root.is_watching();
+            if root.refresh(watcher)? && !was_watching {
+                started.push(root.root.clone());
+            }
+        }
+
+        Ok(started)
     }
 }
 
+// Something that changes when a directory is deleted and recreated.
+#[cfg(unix)]
+type DirIdentity = (u64, u64);
+#[cfg(not(unix))]
+type DirIdentity = ();
+
+#[cfg(unix)]
+fn dir_identity(path: &Path) -> Option<DirIdentity> {
+    use std::os::unix::fs::MetadataExt;
+
+    let meta = std::fs::metadata(path).ok().filter(|m| m.is_dir())?;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_identity(path: &Path) -> Option<DirIdentity> {
+    path.is_dir().then_some(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -242,4 +714,385 @@ mod tests {
             _ => panic!("Expected Deleted event"),
         }
     }
+
+    #[test]
+    fn root_watch_resumes_after_root_is_recreated() {
+        let root = std::env::temp_dir().join(format!("watch_root_{}", uuid::Uuid::new_v4()));
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let mut watcher = notify::recommended_watcher(event_handler(
+            tx,
+            IgnoreSet::default(),
+            WatchOptions::default().extensions,
+        ))
+        .unwrap();
+        let mut root_watch = RootWatch::new(&root);
+
+        // 1. Missing root: nothing to watch yet, and no error either
+        assert!(!root_watch.refresh(&mut watcher).unwrap());
+
+        // 2. Root appears: watch is registered
+        std::fs::create_dir_all(&root).unwrap();
+        assert!(root_watch.refresh(&mut watcher).unwrap());
+
+        // 3. Root deleted: supervisor notices and drops the watch
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(!root_watch.refresh(&mut watcher).unwrap());
+
+        // 4. Root recreated: watching resumes
+        std::fs::create_dir_all(&root).unwrap();
+        assert!(root_watch.refresh(&mut watcher).unwrap());
+
+        // Drain anything left over from the delete/recreate dance
+        while rx.try_recv().is_ok() {}
+
+        // 5. Events for the new directory arrive again
+        let note = root.join("fresh.md");
+        std::fs::write(&note, "hello").unwrap();
+
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        match event {
+            IndexEvent::Created(path) | IndexEvent::Modified(path) => {
+                assert_eq!(path.file_name(), note.file_name());
+            }
+            other => panic!("Expected a create/modify event, got {:?}", other),
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn existing_files_are_emitted_as_created_events() {
+        let root = std::env::temp_dir().join(format!("watch_existing_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        std::fs::write(root.join("top.txt"), "a").unwrap();
+        std::fs::write(root.join("nested").join("deep.md"), "b").unwrap();
+        std::fs::write(root.join("image.png"), "c").unwrap();
+
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let sent = emit_existing_files(&root, &tx).unwrap();
+        assert_eq!(sent, 2);
+
+        let mut paths: Vec<PathBuf> = rx
+            .try_iter()
+            .map(|event| match event {
+                IndexEvent::Created(path) => path,
+                other => panic!("Expected Created event, got {:?}", other),
+            })
+            .collect();
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            vec![root.join("nested").join("deep.md"), root.join("top.txt")]
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn glob_watch_only_watches_matching_directories() {
+        let base = std::env::temp_dir().join(format!("watch_glob_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("alpha").join("notes")).unwrap();
+        std::fs::create_dir_all(base.join("beta").join("notes")).unwrap();
+        std::fs::create_dir_all(base.join("gamma").join("drafts")).unwrap();
+
+        let pattern = format!("{}/*/notes", base.display());
+        let (tx, _rx) = mpsc::channel::<IndexEvent>();
+        let mut watcher = notify::recommended_watcher(event_handler(
+            tx,
+            IgnoreSet::default(),
+            WatchOptions::default().extensions,
+        ))
+        .unwrap();
+        let mut glob_watch = GlobWatch::new(&pattern).unwrap();
+
+        // 1. Startup expansion: two of the three siblings match
+        let started = glob_watch.refresh(&mut watcher).unwrap();
+        let expected = vec![
+            base.join("alpha").join("notes"),
+            base.join("beta").join("notes"),
+        ];
+        assert_eq!(started, expected);
+        assert_eq!(glob_watch.watched_roots(), expected);
+
+        // 2. Nothing new on the next tick
+        assert!(glob_watch.refresh(&mut watcher).unwrap().is_empty());
+
+        // 3. A matching directory created later is picked up
+        std::fs::create_dir_all(base.join("gamma").join("notes")).unwrap();
+        let started = glob_watch.refresh(&mut watcher).unwrap();
+        assert_eq!(started, vec![base.join("gamma").join("notes")]);
+        assert_eq!(glob_watch.watched_roots().len(), 3);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn glob_watch_rejects_invalid_patterns() {
+        assert!(GlobWatch::new("notes/[").is_err());
+    }
+
+    #[test]
+    fn create_then_modify_is_coalesced_into_one_event() {
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let note = PathBuf::from("notes/draft.md");
+        let other = PathBuf::from("notes/other.md");
+
+        tx.send(IndexEvent::Created(note.clone())).unwrap();
+        tx.send(IndexEvent::Modified(other.clone())).unwrap();
+        tx.send(IndexEvent::Modified(note.clone())).unwrap();
+        tx.send(IndexEvent::Modified(note.clone())).unwrap();
+        drop(tx);
+
+        let batch = recv_coalesced(&rx, Duration::from_millis(50)).unwrap();
+        assert_eq!(
+            batch,
+            vec![IndexEvent::Created(note), IndexEvent::Modified(other)]
+        );
+
+        // Channel is drained and closed
+        assert_eq!(recv_coalesced(&rx, Duration::from_millis(50)), None);
+    }
+
+    #[test]
+    fn delete_between_writes_is_kept() {
+        let note = PathBuf::from("note.txt");
+        let events = vec![
+            IndexEvent::Created(note.clone()),
+            IndexEvent::Deleted(note.clone()),
+            IndexEvent::Created(note.clone()),
+            IndexEvent::Modified(note.clone()),
+        ];
+
+        assert_eq!(coalesce_events(events.clone()), events[..3].to_vec());
+    }
+
+    #[test]
+    fn saved_status_follows_a_processed_create_event() {
+        let dir = std::env::temp_dir().join(format!("apply_events_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        std::fs::write(&note, "rust watcher").unwrap();
+        let index_path = dir.join("index.json");
+
+        let shared_index = Mutex::new(Index::new());
+        let (status_tx, status_rx) = mpsc::channel();
+
+        apply_events(
+            &shared_index,
+            vec![IndexEvent::Created(note.clone())],
+            &index_path,
+            None,
+            Some(&status_tx),
+        );
+
+        assert_eq!(
+            status_rx.try_recv(),
+            Ok(IndexStatus::Saved { documents: 1 })
+        );
+        assert_eq!(
+            shared_index.lock().unwrap().search_query("watcher").len(),
+            1
+        );
+        assert_eq!(
+            Index::load_from_disk(&index_path).unwrap().document_count(),
+            1
+        );
+
+        // Deleting it is saved (and reported) too
+        apply_events(
+            &shared_index,
+            vec![IndexEvent::Deleted(note)],
+            &index_path,
+            None,
+            Some(&status_tx),
+        );
+        assert_eq!(
+            status_rx.try_recv(),
+            Ok(IndexStatus::Saved { documents: 0 })
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn binary_index_is_rewritten_with_each_batch() {
+        let dir = std::env::temp_dir().join(format!("apply_events_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let note = dir.join("note.md");
+        std::fs::write(&note, "rust watcher").unwrap();
+        let index_path = dir.join("index.json");
+        let binary_path = dir.join("index.bin");
+
+        let shared_index = Mutex::new(Index::new());
+        apply_events(
+            &shared_index,
+            vec![IndexEvent::Created(note.clone())],
+            &index_path,
+            Some(&binary_path),
+            None,
+        );
+        assert_eq!(
+            Index::open_mmap(&binary_path)
+                .unwrap()
+                .search_query("watcher")
+                .len(),
+            1
+        );
+
+        apply_events(
+            &shared_index,
+            vec![IndexEvent::Deleted(note)],
+            &index_path,
+            Some(&binary_path),
+            None,
+        );
+        assert!(
+            Index::open_mmap(&binary_path)
+                .unwrap()
+                .search_query("watcher")
+                .is_empty()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Keeps every log record; installed once for the whole test binary
+    struct CapturingLogger {
+        records: std::sync::Mutex<Vec<(log::Level, String)>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: std::sync::Mutex::new(Vec::new()),
+    };
+
+    fn captured_logs() -> &'static CapturingLogger {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        &LOGGER
+    }
+
+    #[test]
+    fn notify_errors_are_logged() {
+        let logger = captured_logs();
+        let (tx, _rx) = mpsc::channel::<IndexEvent>();
+
+        let mut handler =
+            event_handler(tx, IgnoreSet::default(), WatchOptions::default().extensions);
+        handler(Err(notify::Error::generic("disk on fire")));
+
+        // Other tests may log concurrently, so look for ours among them
+        let records = logger.records.lock().unwrap();
+        assert!(
+            records
+                .iter()
+                .any(|(level, message)| *level == log::Level::Error
+                    && message.contains("disk on fire")),
+            "{records:?}"
+        );
+    }
+
+    #[test]
+    fn event_extensions_match_ingestion_ignoring_case() {
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let mut handler =
+            event_handler(tx, IgnoreSet::default(), WatchOptions::default().extensions);
+
+        let event = Event::new(EventKind::Modify(notify::event::ModifyKind::Any))
+            .add_path(PathBuf::from("notes/NOTE.TXT"))
+            .add_path(PathBuf::from("notes/Todo.Md"))
+            .add_path(PathBuf::from("notes/main.rs"));
+        handler(Ok(event));
+
+        let events: Vec<IndexEvent> = rx.try_iter().collect();
+        assert_eq!(
+            events,
+            vec![
+                IndexEvent::Modified(PathBuf::from("notes/NOTE.TXT")),
+                IndexEvent::Modified(PathBuf::from("notes/Todo.Md")),
+            ]
+        );
+
+        // Configurable, like LoadOptions::extensions
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let mut handler = event_handler(tx, IgnoreSet::default(), vec!["org".to_string()]);
+        let event = Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("notes/plan.org"))
+            .add_path(PathBuf::from("notes/plan.md"));
+        handler(Ok(event));
+        assert_eq!(
+            rx.try_iter().collect::<Vec<_>>(),
+            vec![IndexEvent::Created(PathBuf::from("notes/plan.org"))]
+        );
+    }
+
+    #[test]
+    fn events_for_ignored_paths_are_not_emitted() {
+        // On unless turned off, like initial ingestion
+        assert!(WatchOptions::default().respect_ignore_files);
+
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let ignore = IgnoreSet::default();
+        set_ignore_rules(
+            &ignore,
+            IgnoreRules::parse(Path::new("notes"), "target/\n*.draft.md\n"),
+        );
+        let mut handler = event_handler(tx, ignore, WatchOptions::default().extensions);
+
+        let event = Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("notes/target/out.md"))
+            .add_path(PathBuf::from("notes/idea.draft.md"));
+        handler(Ok(event));
+        assert!(rx.try_recv().is_err());
+
+        // Everything else still gets through
+        let event = Event::new(EventKind::Create(notify::event::CreateKind::File))
+            .add_path(PathBuf::from("notes/idea.md"));
+        handler(Ok(event));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            IndexEvent::Created(PathBuf::from("notes/idea.md"))
+        );
+    }
+
+    #[test]
+    fn emit_existing_skips_ignored_files_and_directories() {
+        let root = std::env::temp_dir().join(format!("ignored_notes_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("target")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.draft.md\n").unwrap();
+        std::fs::write(root.join("target/out.md"), "build output").unwrap();
+        std::fs::write(root.join("idea.draft.md"), "draft").unwrap();
+        std::fs::write(root.join("idea.md"), "idea").unwrap();
+
+        let (tx, rx) = mpsc::channel::<IndexEvent>();
+        let sent =
+            emit_existing_filtered(&root, &tx, &load_ignore_rules(&root), DEFAULT_EXTENSIONS)
+                .unwrap();
+
+        assert_eq!(sent, 1);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            IndexEvent::Created(root.join("idea.md"))
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
 }
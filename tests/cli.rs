@@ -0,0 +1,257 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use uuid::Uuid;
+
+// Fresh working directory per test, so index.json / notes never leak between runs
+fn make_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run_cli(dir: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rust-knowledge-search"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .expect("failed to run the CLI binary")
+}
+
+fn run_cli_with_stdin(dir: &PathBuf, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-knowledge-search"))
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run the CLI binary");
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn tokens_command_prints_tokens_of_a_file() {
+    let dir = make_temp_dir("cli_tokens");
+    fs::write(dir.join("note.md"), "Hello, World! hello again.").unwrap();
+
+    let output = run_cli(&dir, &["tokens", "note.md"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let tokens: Vec<&str> = stdout.lines().collect();
+    assert_eq!(tokens, vec!["hello", "world", "hello", "again"]);
+
+    let output = run_cli(&dir, &["tokens", "note.md", "--counts"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().next(), Some("hello 2"));
+    assert!(stdout.contains("world 1"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_on_empty_index_says_so() {
+    let dir = make_temp_dir("cli_empty_search");
+
+    let output = run_cli(&dir, &["search", "rust"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Index is empty"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_with_only_punctuation_reports_no_terms() {
+    let dir = make_temp_dir("cli_punctuation_search");
+
+    let output = run_cli(&dir, &["search", "!!! ..."]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Query contained no searchable terms"));
+    assert!(!stdout.contains("Found"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn index_command_accepts_a_single_file() {
+    let dir = make_temp_dir("cli_index_file");
+    fs::write(dir.join("note.md"), "ferris the crab").unwrap();
+
+    let output = run_cli(&dir, &["index", "note.md"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Indexed 1 documents"));
+
+    let output = run_cli(&dir, &["search", "ferris"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Found 1 results"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_count_prints_only_the_number_of_matches() {
+    let dir = make_temp_dir("cli_search_count");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs").join("a.md"), "rust notes").unwrap();
+    fs::write(dir.join("docs").join("b.txt"), "more rust").unwrap();
+    fs::write(dir.join("docs").join("c.txt"), "gardening").unwrap();
+
+    assert!(run_cli(&dir, &["index", "docs"]).status.success());
+
+    let output = run_cli(&dir, &["search", "rust", "--count"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+
+    let output = run_cli(&dir, &["search", "missing", "--count"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_reads_the_query_from_stdin_when_omitted() {
+    let dir = make_temp_dir("cli_stdin_query");
+    fs::write(dir.join("note.md"), "rust async runtimes").unwrap();
+    assert!(run_cli(&dir, &["index", "note.md"]).status.success());
+
+    let output = run_cli_with_stdin(&dir, &["search", "--count"], "rust async\n");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+
+    let output = run_cli_with_stdin(&dir, &["search", "--count"], "gardening\n");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn search_mode_switches_between_any_and_all_terms() {
+    let dir = make_temp_dir("cli_search_mode");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs").join("a.md"), "rust async runtimes").unwrap();
+    fs::write(dir.join("docs").join("b.txt"), "rust ownership").unwrap();
+    fs::write(dir.join("docs").join("c.txt"), "async javascript").unwrap();
+    assert!(run_cli(&dir, &["index", "docs"]).status.success());
+
+    // Default is or
+    let output = run_cli(&dir, &["search", "rust async", "--count"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "3\n");
+
+    let output = run_cli(&dir, &["search", "rust async", "--count", "--mode", "or"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "3\n");
+
+    let output = run_cli(&dir, &["search", "rust async", "--mode", "and"]);
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Found 1 results")
+    );
+
+    assert!(
+        !run_cli(&dir, &["search", "rust", "--mode", "xor"])
+            .status
+            .success()
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn binary_index_format_is_written_and_searched() {
+    let dir = make_temp_dir("cli_index_format");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs").join("a.md"), "rust async runtimes").unwrap();
+    fs::write(dir.join("docs").join("b.txt"), "rust ownership").unwrap();
+    fs::write(dir.join("docs").join("c.txt"), "async javascript").unwrap();
+
+    let output = run_cli(&dir, &["index", "docs", "--index-format", "binary"]);
+    assert!(output.status.success());
+    assert!(dir.join("index.bin").exists());
+    assert!(dir.join("index.json").exists());
+
+    let output = run_cli(
+        &dir,
+        &["search", "rust", "--count", "--index-format", "binary"],
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+
+    let output = run_cli(
+        &dir,
+        &[
+            "search",
+            "rust async",
+            "--count",
+            "--mode",
+            "and",
+            "--index-format",
+            "binary",
+        ],
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1\n");
+
+    // Without the flag the binary file is picked up on its own, even with
+    // the JSON index gone
+    fs::remove_file(dir.join("index.json")).unwrap();
+    let output = run_cli(&dir, &["search", "async", "--count"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn one_shot_commands_print_only_their_own_output() {
+    let dir = make_temp_dir("cli_clean_stdout");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(dir.join("notes/a.md"), "rust ownership").unwrap();
+    fs::write(dir.join("notes/b.md"), "rust async").unwrap();
+
+    let output = run_cli(&dir, &["index", "notes"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Indexed 2 documents\n"
+    );
+
+    // A deleted note isn't reconciled (or saved) behind a one-shot command
+    fs::remove_file(dir.join("notes/b.md")).unwrap();
+    let output = run_cli(&dir, &["search", "rust", "--count"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn index_skips_files_the_notes_gitignore_excludes() {
+    let dir = make_temp_dir("cli_gitignore");
+    fs::create_dir_all(dir.join("notes")).unwrap();
+    fs::write(dir.join("notes/.gitignore"), "*.draft.md\n").unwrap();
+    fs::write(dir.join("notes/idea.md"), "rust ownership").unwrap();
+    fs::write(dir.join("notes/idea.draft.md"), "rust draft").unwrap();
+
+    let output = run_cli(&dir, &["index", "notes"]);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Indexed 1 documents\n"
+    );
+
+    let output = run_cli(&dir, &["search", "draft", "--count"]);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "0\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}